@@ -0,0 +1,38 @@
+use async_trait::async_trait;
+
+use crate::request::{AuthRequest, SignRequest};
+use crate::response::{CollectResponse, OrderResponse};
+use crate::{Client, Error, OrderRef};
+
+/// The RP API operations, implemented by `Client`.
+///
+/// Application code can depend on this trait instead of `Client` to swap in a mock in tests.
+#[async_trait]
+pub trait BankIdApi: Send + Sync {
+    async fn auth(&self, request: AuthRequest) -> Result<OrderResponse, Error>;
+
+    async fn sign(&self, request: SignRequest) -> Result<OrderResponse, Error>;
+
+    async fn collect(&self, order_ref: OrderRef) -> Result<CollectResponse, Error>;
+
+    async fn cancel(&self, order_ref: OrderRef) -> Result<(), Error>;
+}
+
+#[async_trait]
+impl BankIdApi for Client {
+    async fn auth(&self, request: AuthRequest) -> Result<OrderResponse, Error> {
+        Client::auth(self, request).await
+    }
+
+    async fn sign(&self, request: SignRequest) -> Result<OrderResponse, Error> {
+        Client::sign(self, request).await
+    }
+
+    async fn collect(&self, order_ref: OrderRef) -> Result<CollectResponse, Error> {
+        Client::collect(self, order_ref).await
+    }
+
+    async fn cancel(&self, order_ref: OrderRef) -> Result<(), Error> {
+        Client::cancel(self, order_ref).await
+    }
+}
@@ -0,0 +1,84 @@
+use reqwest::Url;
+
+use crate::response::OrderResponse;
+use crate::AutoStartToken;
+
+const APP_LINK: &str = "https://app.bankid.com/";
+const DESKTOP_LINK: &str = "bankid:///";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    Ios,
+    Android,
+    Desktop,
+}
+
+/// Builds the URL that launches the BankID app on the same device.
+///
+/// Mobile platforms use the universal link, desktop uses the `bankid:` scheme. The redirect is
+/// where the app sends the user when done. It's only used on iOS: desktop can't redirect, and on
+/// Android the app returns to the calling app by itself, so a redirect there is logged and
+/// ignored.
+pub fn launch_url(
+    auto_start_token: &AutoStartToken,
+    platform: Platform,
+    redirect: Option<&str>,
+) -> Url {
+    let (base, redirect) = match platform {
+        Platform::Ios => (APP_LINK, redirect.unwrap_or("null")),
+        Platform::Android => {
+            if let Some(redirect) = redirect {
+                log::warn!("Ignoring redirect {} in an Android launch url", redirect);
+            }
+
+            (APP_LINK, "null")
+        }
+        Platform::Desktop => (DESKTOP_LINK, "null"),
+    };
+
+    // Both links are constants.
+    #[allow(clippy::expect_used)]
+    let mut url = Url::parse(base).expect("Invalid BankID launch url");
+
+    url.query_pairs_mut()
+        .append_pair("autostarttoken", &auto_start_token.to_string())
+        .append_pair("redirect", redirect);
+
+    url
+}
+
+impl OrderResponse {
+    pub fn launch_url(&self, platform: Platform, redirect: Option<&str>) -> Url {
+        launch_url(&self.auto_start_token, platform, redirect)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{launch_url, Platform};
+    use crate::{AutoStartToken, Uuid};
+
+    #[test]
+    fn test_launch_url() {
+        let token = Uuid::parse_str("46f5ae5a-4fd8-4b3c-b6a0-e46bfca8cd4b")
+            .map(AutoStartToken::from)
+            .expect("Invalid uuid");
+
+        assert_eq!(
+            launch_url(&token, Platform::Ios, Some("https://example.com/return?id=1")).as_str(),
+            "https://app.bankid.com/?autostarttoken=46f5ae5a-4fd8-4b3c-b6a0-e46bfca8cd4b&redirect=https%3A%2F%2Fexample.com%2Freturn%3Fid%3D1"
+        );
+        assert_eq!(
+            launch_url(&token, Platform::Android, None).as_str(),
+            "https://app.bankid.com/?autostarttoken=46f5ae5a-4fd8-4b3c-b6a0-e46bfca8cd4b&redirect=null"
+        );
+        assert_eq!(
+            launch_url(&token, Platform::Android, Some("https://example.com")).as_str(),
+            "https://app.bankid.com/?autostarttoken=46f5ae5a-4fd8-4b3c-b6a0-e46bfca8cd4b&redirect=null"
+        );
+        assert_eq!(
+            launch_url(&token, Platform::Desktop, Some("https://example.com")).as_str(),
+            "bankid:///?autostarttoken=46f5ae5a-4fd8-4b3c-b6a0-e46bfca8cd4b&redirect=null"
+        );
+    }
+}
@@ -0,0 +1,153 @@
+//! A synchronous client built on `reqwest::blocking`, for batch jobs and tools that don't run an
+//! async runtime.
+//!
+//! Like `reqwest::blocking`, it must not be used from within an async runtime.
+
+use std::io::Read;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::request::{self, AuthRequest, SignRequest};
+use crate::response::{CancelResponse, CollectResponse, OrderResponse};
+use crate::{ApiPath, ClientBuilder, Endpoint, Error, HttpStatus, OrderRef};
+
+#[derive(Debug)]
+pub struct Client {
+    http: reqwest::blocking::Client,
+    // Holds the configuration and maintenance tracking shared with the async client.
+    inner: crate::Client,
+}
+
+impl ClientBuilder {
+    /// Builds a blocking client. Middleware, retries and request limits only apply to the async
+    /// client.
+    #[cfg_attr(docsrs, doc(cfg(feature = "blocking")))]
+    #[allow(clippy::expect_used)]
+    pub fn build_blocking(self) -> Client {
+        self.try_build_blocking()
+            .expect("Failed to create blocking client")
+    }
+
+    #[cfg_attr(docsrs, doc(cfg(feature = "blocking")))]
+    pub fn try_build_blocking(self) -> Result<Client, Error> {
+        let http = reqwest::blocking::ClientBuilder::from(self.reqwest_builder()?)
+            .build()
+            .map_err(|err| {
+                Error::Configuration(format!("Failed to create HTTP client: {}", err))
+            })?;
+
+        Ok(Client {
+            http,
+            inner: self.try_build()?,
+        })
+    }
+}
+
+impl Client {
+    pub fn new(endpoint: Endpoint) -> Client {
+        ClientBuilder::new(endpoint).build_blocking()
+    }
+
+    pub fn try_new(endpoint: Endpoint) -> Result<Client, Error> {
+        ClientBuilder::new(endpoint).try_build_blocking()
+    }
+
+    pub fn auth(&self, request: AuthRequest) -> Result<OrderResponse, Error> {
+        let body = self.inner.auth_body(request)?;
+
+        self.send(ApiPath::Auth, &body)
+    }
+
+    pub fn sign(&self, request: SignRequest) -> Result<OrderResponse, Error> {
+        let body = self.inner.sign_body(request)?;
+
+        self.send(ApiPath::Sign, &body)
+    }
+
+    pub fn collect(&self, order_ref: OrderRef) -> Result<CollectResponse, Error> {
+        self.send(ApiPath::Collect, &request::CollectRequest { order_ref })
+    }
+
+    pub fn cancel(&self, order_ref: OrderRef) -> Result<(), Error> {
+        self.send::<_, CancelResponse>(ApiPath::Cancel, &request::CancelRequest { order_ref })
+            .map(|_| ())
+    }
+
+    fn send<B, T>(&self, path: ApiPath, body: &B) -> Result<T, Error>
+    where
+        B: Serialize,
+        T: DeserializeOwned,
+    {
+        self.inner.log_request(path, body)?;
+
+        let response = self.http.post(self.inner.url(path)).json(body).send()?;
+
+        let status = HttpStatus::new(response.status().as_u16());
+        let body = read_body(response, self.inner.max_response_size)?;
+
+        self.inner.decode(status, &body)
+    }
+}
+
+fn read_body(response: reqwest::blocking::Response, limit: usize) -> Result<Vec<u8>, Error> {
+    if matches!(response.content_length(), Some(length) if length > limit as u64) {
+        return Err(Error::ResponseTooLarge { limit });
+    }
+
+    let mut body = Vec::new();
+    response.take(limit as u64 + 1).read_to_end(&mut body)?;
+
+    if body.len() > limit {
+        return Err(Error::ResponseTooLarge { limit });
+    }
+
+    Ok(body)
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use super::Client;
+    use crate::golden::{COLLECT_PENDING, ORDER_RESPONSE};
+    use crate::mock::{auth_request, MockRp};
+    use crate::response::{CollectResponse, ErrorCode};
+    use crate::{ApiPath, ClientBuilder, Error};
+
+    #[test]
+    fn test_blocking_client() {
+        let runtime = tokio::runtime::Runtime::new().expect("Failed to start runtime");
+
+        let rp = runtime.block_on(async {
+            let rp = MockRp::start().await;
+
+            rp.respond(ApiPath::Auth, ORDER_RESPONSE).await;
+            rp.respond(ApiPath::Collect, COLLECT_PENDING).await;
+            rp.respond_error(ApiPath::Cancel, 400, ErrorCode::Canceled, "Cancelled")
+                .await;
+
+            rp
+        });
+
+        let client = Client::new(rp.endpoint());
+
+        let order = client.auth(auth_request(None)).expect("Auth failed");
+
+        assert!(matches!(
+            client.collect(order.order_ref),
+            Ok(CollectResponse::Pending { .. })
+        ));
+        assert!(matches!(
+            client.cancel(order.order_ref),
+            Err(Error::ClientError { .. })
+        ));
+
+        let client = ClientBuilder::new(rp.endpoint())
+            .max_response_size(16)
+            .build_blocking();
+
+        assert!(matches!(
+            client.collect(order.order_ref),
+            Err(Error::ResponseTooLarge { limit: 16 })
+        ));
+    }
+}
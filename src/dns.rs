@@ -0,0 +1,91 @@
+use std::fmt;
+use std::sync::Arc;
+
+use reqwest::dns::Resolve;
+
+use crate::ClientBuilder;
+
+/// Installs a user supplied resolver on the underlying reqwest client.
+#[derive(Clone)]
+pub(crate) struct DnsResolver(
+    Arc<dyn Fn(reqwest::ClientBuilder) -> reqwest::ClientBuilder + Send + Sync>,
+);
+
+impl DnsResolver {
+    pub(crate) fn apply(&self, builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        (self.0)(builder)
+    }
+}
+
+impl fmt::Debug for DnsResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DnsResolver")
+    }
+}
+
+impl ClientBuilder {
+    /// Resolves the BankID hostnames with `resolver` instead of the system resolver.
+    #[cfg_attr(docsrs, doc(cfg(feature = "dns")))]
+    pub fn dns_resolver<R>(mut self, resolver: Arc<R>) -> ClientBuilder
+    where
+        R: Resolve + 'static,
+    {
+        self.dns_resolver = Some(DnsResolver(Arc::new(move |builder| {
+            builder.dns_resolver(resolver.to_owned())
+        })));
+        self
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use hyper::client::connect::dns::Name;
+    use reqwest::dns::{Addrs, Resolve, Resolving};
+
+    use crate::golden::COLLECT_PENDING;
+    use crate::mock::MockRp;
+    use crate::{ApiPath, Client, Endpoint, Uuid};
+
+    struct StaticResolver {
+        addr: SocketAddr,
+        lookups: AtomicUsize,
+    }
+
+    impl Resolve for StaticResolver {
+        fn resolve(&self, _name: Name) -> Resolving {
+            self.lookups.fetch_add(1, Ordering::SeqCst);
+
+            let addrs: Addrs = Box::new(std::iter::once(self.addr));
+            Box::pin(async move { Ok(addrs) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dns_resolver() {
+        let rp = MockRp::start().await;
+        rp.respond(ApiPath::Collect, COLLECT_PENDING).await;
+
+        let resolver = Arc::new(StaticResolver {
+            addr: *rp.server().address(),
+            lookups: AtomicUsize::new(0),
+        });
+
+        let client = Client::builder(Endpoint::Mock(format!(
+            "http://appapi2.test.bankid.invalid:{}/rp/",
+            rp.server().address().port()
+        )))
+        .dns_resolver(resolver.to_owned())
+        .build();
+
+        client
+            .collect(Uuid::nil().into())
+            .await
+            .expect("Collect failed");
+
+        assert_eq!(resolver.lookups.load(Ordering::SeqCst), 1);
+    }
+}
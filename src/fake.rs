@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use async_trait::async_trait;
+
+use crate::request::{AuthRequest, SignRequest};
+use crate::response::{
+    ClientError, CollectHintCode, CollectResponse, CompletionData, Device, ErrorCode,
+    OrderResponse, User,
+};
+use crate::{BankIdApi, Error, HttpStatus, OrderRef, PersonalNumber, Uuid};
+
+/// Where the fake gets order refs and tokens from.
+pub trait IdSource: Debug + Send + Sync {
+    fn next_id(&self) -> Uuid;
+}
+
+/// Random v4 UUIDs, the default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomIds;
+
+impl IdSource for RandomIds {
+    fn next_id(&self) -> Uuid {
+        Uuid::new_v4()
+    }
+}
+
+/// UUIDs counting up from 1, for deterministic tests and snapshots.
+#[derive(Debug, Default)]
+pub struct SequentialIds(AtomicU64);
+
+impl IdSource for SequentialIds {
+    fn next_id(&self) -> Uuid {
+        Uuid::from_u128(u128::from(self.0.fetch_add(1, Ordering::SeqCst) + 1))
+    }
+}
+
+/// One collect result in a scripted flow.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone)]
+pub enum FakeStep {
+    Pending(CollectHintCode),
+    Failed(CollectHintCode),
+    Complete(CompletionData),
+}
+
+#[derive(Debug)]
+struct FakeOrder {
+    steps: Vec<FakeStep>,
+    position: usize,
+}
+
+/// An in-memory stand-in for the RP API.
+///
+/// Every order started on the fake walks through the same scripted flow, one step per
+/// `collect`. The last step repeats once reached. Cancelled and unknown orders answer like
+/// BankID does, with `invalidParameters`.
+#[derive(Debug)]
+pub struct FakeClient {
+    flow: Vec<FakeStep>,
+    ids: Arc<dyn IdSource>,
+    orders: Mutex<HashMap<OrderRef, FakeOrder>>,
+}
+
+impl FakeClient {
+    /// A fake where every order goes through `flow`, which must not be empty.
+    pub fn new(flow: Vec<FakeStep>) -> FakeClient {
+        assert!(!flow.is_empty(), "A fake flow needs at least one step");
+
+        FakeClient {
+            flow,
+            ids: Arc::new(RandomIds),
+            orders: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Generates order refs and tokens from `ids` instead of randomly.
+    pub fn with_id_source<I>(mut self, ids: I) -> FakeClient
+    where
+        I: IdSource + 'static,
+    {
+        self.ids = Arc::new(ids);
+        self
+    }
+
+    /// A fake where orders are pending with `outstandingTransaction`, then `userSign`, and then
+    /// complete with `completion_data`.
+    pub fn completing_with(completion_data: CompletionData) -> FakeClient {
+        FakeClient::new(vec![
+            FakeStep::Pending(CollectHintCode::OutstandingTransaction),
+            FakeStep::Pending(CollectHintCode::UserSign),
+            FakeStep::Complete(completion_data),
+        ])
+    }
+
+    /// Number of orders that are started and not cancelled.
+    pub fn open_orders(&self) -> usize {
+        self.orders().len()
+    }
+
+    fn orders(&self) -> MutexGuard<'_, HashMap<OrderRef, FakeOrder>> {
+        self.orders
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn start(&self) -> OrderResponse {
+        let order = OrderResponse {
+            order_ref: self.ids.next_id().into(),
+            auto_start_token: self.ids.next_id().into(),
+            qr_start_token: self.ids.next_id().into(),
+            qr_start_secret: self.ids.next_id().into(),
+        };
+
+        self.orders().insert(
+            order.order_ref,
+            FakeOrder {
+                steps: self.flow.to_owned(),
+                position: 0,
+            },
+        );
+
+        order
+    }
+}
+
+fn no_such_order() -> Error {
+    Error::ClientError {
+        status: HttpStatus::new(400),
+        error: ClientError {
+            error_code: ErrorCode::InvalidParameters,
+            details: "No such order".to_owned(),
+        },
+    }
+}
+
+/// Completion data for `personal_number` with placeholder values for everything else.
+pub fn completion_data(personal_number: PersonalNumber) -> CompletionData {
+    CompletionData {
+        user: User {
+            personal_number,
+            name: "Tester Testsson".to_owned(),
+            given_name: "Tester".to_owned(),
+            surname: "Testsson".to_owned(),
+        },
+        device: Device {
+            ip_address: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            uhi: None,
+        },
+        cert: None,
+        signature: String::new(),
+        ocsp_response: String::new(),
+        risk: None,
+        bank_id_issue_date: None,
+        step_up: None,
+    }
+}
+
+#[async_trait]
+impl BankIdApi for FakeClient {
+    async fn auth(&self, _request: AuthRequest) -> Result<OrderResponse, Error> {
+        Ok(self.start())
+    }
+
+    async fn sign(&self, _request: SignRequest) -> Result<OrderResponse, Error> {
+        Ok(self.start())
+    }
+
+    async fn collect(&self, order_ref: OrderRef) -> Result<CollectResponse, Error> {
+        let mut orders = self.orders();
+
+        let order = orders.get_mut(&order_ref).ok_or_else(no_such_order)?;
+        let step = order.steps[order.position].to_owned();
+
+        if order.position + 1 < order.steps.len() {
+            order.position += 1;
+        }
+
+        Ok(match step {
+            FakeStep::Pending(hint_code) => CollectResponse::Pending {
+                hint_code,
+                order_ref,
+            },
+            FakeStep::Failed(hint_code) => CollectResponse::Failed {
+                hint_code,
+                order_ref,
+            },
+            FakeStep::Complete(completion_data) => CollectResponse::Complete {
+                completion_data,
+                order_ref,
+            },
+        })
+    }
+
+    async fn cancel(&self, order_ref: OrderRef) -> Result<(), Error> {
+        self.orders()
+            .remove(&order_ref)
+            .map(|_| ())
+            .ok_or_else(no_such_order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{completion_data, FakeClient, SequentialIds};
+    use crate::mock::auth_request;
+    use crate::response::{CollectHintCode, CollectResponse};
+    use crate::{testenv, BankIdApi, Uuid};
+
+    #[tokio::test]
+    async fn test_fake_client_flow() {
+        let personal_number = testenv::DEMO_PERSONAL_NUMBERS[0];
+        let client = FakeClient::completing_with(completion_data(personal_number))
+            .with_id_source(SequentialIds::default());
+
+        let order = client.auth(auth_request(None)).await.expect("Auth failed");
+
+        assert_eq!(order.order_ref, Uuid::from_u128(1).into());
+        assert_eq!(order.qr_start_secret, Uuid::from_u128(4).into());
+
+        for expected in [
+            CollectHintCode::OutstandingTransaction,
+            CollectHintCode::UserSign,
+        ] {
+            match client.collect(order.order_ref).await {
+                Ok(CollectResponse::Pending { hint_code, .. }) => assert_eq!(hint_code, expected),
+                other => panic!("Unexpected collect response {:?}", other),
+            }
+        }
+
+        for _ in 0..2 {
+            match client.collect(order.order_ref).await {
+                Ok(CollectResponse::Complete {
+                    completion_data, ..
+                }) => assert_eq!(completion_data.user.personal_number, personal_number),
+                other => panic!("Unexpected collect response {:?}", other),
+            }
+        }
+
+        client.cancel(order.order_ref).await.expect("Cancel failed");
+        assert!(client.collect(order.order_ref).await.is_err());
+        assert!(client.cancel(order.order_ref).await.is_err());
+        assert_eq!(client.open_orders(), 0);
+    }
+}
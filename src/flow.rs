@@ -0,0 +1,690 @@
+use std::fmt;
+use std::time::{Duration, Instant, SystemTime};
+
+use futures::future::join_all;
+use futures::stream::{self, Stream};
+
+use crate::request::{AuthRequest, SignRequest};
+use crate::response::{ClientError, CollectHintCode, CollectResponse, CompletionData, ErrorCode};
+use crate::state::{CollectStateMachine, Transition};
+use crate::telemetry::{Phase, PhaseEvent, RequestContext};
+use crate::{Client, ClientBuilder, Error, Metadata, OrderRef};
+
+/// Collect interval recommended by BankID.
+pub const COLLECT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often and for how long an order is collected.
+///
+/// While the hint code is `outstandingTransaction`, i.e. the user hasn't opened the app yet, the
+/// interval can be multiplied by a backoff factor after every collect, up to a maximum interval.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PollPolicy {
+    interval: Duration,
+    max_duration: Option<Duration>,
+    backoff_factor: f64,
+    max_interval: Duration,
+}
+
+impl PollPolicy {
+    pub fn new(interval: Duration) -> PollPolicy {
+        PollPolicy {
+            interval,
+            max_duration: None,
+            backoff_factor: 1.0,
+            max_interval: interval,
+        }
+    }
+
+    /// Cancel the order and give up once this much time has passed.
+    pub fn max_duration(mut self, max_duration: Duration) -> PollPolicy {
+        self.max_duration = Some(max_duration);
+        self
+    }
+
+    /// Back off while the transaction is outstanding, `factor` should be at least 1.
+    pub fn outstanding_transaction_backoff(
+        mut self,
+        factor: f64,
+        max_interval: Duration,
+    ) -> PollPolicy {
+        self.backoff_factor = factor.max(1.0);
+        self.max_interval = max_interval.max(self.interval);
+        self
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub fn deadline(&self) -> Option<Duration> {
+        self.max_duration
+    }
+
+    // The policy with `deadline` as its max duration, unless it already has one.
+    fn or_deadline(self, deadline: Option<Duration>) -> PollPolicy {
+        PollPolicy {
+            max_duration: self.max_duration.or(deadline),
+            ..self
+        }
+    }
+
+    /// The interval to wait after a pending collect with `hint_code`, given the previous interval.
+    pub fn next_interval(&self, previous: Duration, hint_code: &CollectHintCode) -> Duration {
+        match hint_code {
+            CollectHintCode::OutstandingTransaction => previous
+                .mul_f64(self.backoff_factor)
+                .clamp(self.interval, self.max_interval),
+            _ => self.interval,
+        }
+    }
+}
+
+impl Default for PollPolicy {
+    fn default() -> Self {
+        PollPolicy::new(COLLECT_INTERVAL)
+    }
+}
+
+/// Why the relying party cancelled an order, as opposed to the user cancelling it in the app.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CancelReason {
+    /// The order didn't finish in time.
+    Timeout,
+    /// The user left the page or closed the app that started the order.
+    NavigatedAway,
+    /// A newer order replaced this one.
+    Duplicate,
+    Other(String),
+}
+
+impl CancelReason {
+    /// A short name for metrics and audit logs, e.g. "timeout".
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Timeout => "timeout",
+            Self::NavigatedAway => "navigatedAway",
+            Self::Duplicate => "duplicate",
+            Self::Other(reason) => reason,
+        }
+    }
+}
+
+impl fmt::Display for CancelReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A hint code seen while collecting an order, and when it was first seen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObservedHintCode {
+    pub hint_code: CollectHintCode,
+    pub at: SystemTime,
+}
+
+impl ObservedHintCode {
+    fn now(hint_code: CollectHintCode) -> ObservedHintCode {
+        ObservedHintCode {
+            hint_code,
+            at: SystemTime::now(),
+        }
+    }
+}
+
+/// A completed order, with the hint codes seen while collecting it in the order they were seen.
+///
+/// The hint codes show where the user spent their time, e.g. a long `outstandingTransaction`
+/// means they took a while to open the app.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub completion_data: CompletionData,
+    pub hint_codes: Vec<ObservedHintCode>,
+}
+
+#[derive(Debug)]
+pub enum CancelOutcome {
+    Cancelled,
+    /// The order had already completed, failed or expired.
+    AlreadyFinished,
+    Failed(Error),
+}
+
+impl ClientBuilder {
+    /// The max duration of `Client::authenticate` when its `PollPolicy` has none.
+    pub fn auth_deadline(mut self, deadline: Duration) -> ClientBuilder {
+        self.auth_deadline = Some(deadline);
+        self
+    }
+
+    /// The max duration of `Client::sign_and_collect` when its `PollPolicy` has none.
+    ///
+    /// Signing usually means reading the text to sign, so it can warrant a longer deadline than
+    /// authenticating.
+    pub fn sign_deadline(mut self, deadline: Duration) -> ClientBuilder {
+        self.sign_deadline = Some(deadline);
+        self
+    }
+}
+
+impl Client {
+    /// Starts an auth order and collects it according to `policy` until it completes or fails.
+    ///
+    /// Without a max duration in `policy`, the client's auth deadline applies, if one was set.
+    /// The time each phase of the order took is reported to the client's metrics hook.
+    pub async fn authenticate(
+        &self,
+        request: AuthRequest,
+        policy: PollPolicy,
+    ) -> Result<Completion, Error> {
+        let started = Instant::now();
+        let order = self.auth(request).await?;
+        let created = Instant::now();
+        self.record_phase(Phase::CreateOrder, created - started, None);
+        let policy = policy.or_deadline(self.auth_deadline);
+
+        self.wait_for_completion(order.order_ref, policy, created, None)
+            .await
+    }
+
+    /// Starts a sign order and collects it according to `policy` until it completes or fails.
+    ///
+    /// If the order hasn't finished within the policy's max duration, or the client's sign deadline
+    /// if the policy has none, it is cancelled and `Error::DeadlineExceeded` is returned. The time
+    /// each phase of the order took is reported to the client's metrics hook.
+    pub async fn sign_and_collect(
+        &self,
+        request: SignRequest,
+        policy: PollPolicy,
+    ) -> Result<Completion, Error> {
+        let started = Instant::now();
+        let order = self.sign(request).await?;
+        let created = Instant::now();
+        self.record_phase(Phase::CreateOrder, created - started, None);
+        let policy = policy.or_deadline(self.sign_deadline);
+
+        self.wait_for_completion(order.order_ref, policy, created, None)
+            .await
+    }
+
+    /// Collects the order until it completes or fails, `created` is when the order was created.
+    pub(crate) async fn wait_for_completion(
+        &self,
+        order_ref: OrderRef,
+        policy: PollPolicy,
+        created: Instant,
+        metadata: Option<&Metadata>,
+    ) -> Result<Completion, Error> {
+        let context = RequestContext {
+            metadata,
+            ..Default::default()
+        };
+
+        // Kept outside the collect future, so it's still there when the deadline drops it.
+        let mut hint_codes = Vec::new();
+
+        let collect = async {
+            let mut machine = CollectStateMachine::new(order_ref, policy);
+            let mut user_started = None;
+
+            loop {
+                match machine.apply(self.collect_in(order_ref, context).await?) {
+                    Transition::Complete { completion_data } => {
+                        self.record_phase(Phase::Complete, created.elapsed(), metadata);
+
+                        return Ok(completion_data);
+                    }
+                    Transition::Failed { hint_code, .. } => {
+                        hint_codes.push(ObservedHintCode::now(hint_code.to_owned()));
+
+                        return Err(Error::OrderFailed {
+                            order_ref,
+                            hint_code,
+                            hint_codes: std::mem::take(&mut hint_codes),
+                        });
+                    }
+                    Transition::Pending {
+                        hint_code,
+                        changed,
+                        next_collect,
+                        ..
+                    } => {
+                        if changed {
+                            match hint_code {
+                                CollectHintCode::Started => {
+                                    user_started.get_or_insert_with(Instant::now);
+                                }
+                                CollectHintCode::UserSign => {
+                                    if let Some(user_started) = user_started.take() {
+                                        self.record_phase(
+                                            Phase::StartedToUserSign,
+                                            user_started.elapsed(),
+                                            metadata,
+                                        );
+                                    }
+                                }
+                                _ => {}
+                            }
+
+                            hint_codes.push(ObservedHintCode::now(hint_code));
+                        }
+
+                        tokio::time::sleep(next_collect).await;
+                    }
+                }
+            }
+        };
+
+        let completion_data = match policy.deadline() {
+            None => collect.await?,
+            Some(deadline) => match tokio::time::timeout(deadline, collect).await {
+                Ok(result) => result?,
+                Err(_) => return Err(self.give_up(order_ref, hint_codes, context).await),
+            },
+        };
+
+        Ok(Completion {
+            completion_data,
+            hint_codes,
+        })
+    }
+
+    fn record_phase(&self, phase: Phase, duration: Duration, metadata: Option<&Metadata>) {
+        if let Some(hook) = &self.metrics_hook {
+            hook.record_phase(&PhaseEvent {
+                phase,
+                duration,
+                metadata: metadata.cloned(),
+            });
+        }
+    }
+
+    async fn give_up(
+        &self,
+        order_ref: OrderRef,
+        hint_codes: Vec<ObservedHintCode>,
+        context: RequestContext<'_>,
+    ) -> Error {
+        let context = RequestContext {
+            cancel_reason: Some(&CancelReason::Timeout),
+            ..context
+        };
+
+        // Best effort, the order expires on its own if cancelling fails.
+        let _ = self.cancel_in(order_ref, context).await;
+
+        Error::DeadlineExceeded {
+            order_ref,
+            hint_codes,
+        }
+    }
+
+    /// Cancels several orders concurrently, returning the outcome for each order ref.
+    pub async fn cancel_many<I>(&self, order_refs: I) -> Vec<(OrderRef, CancelOutcome)>
+    where
+        I: IntoIterator<Item = OrderRef>,
+    {
+        join_all(order_refs.into_iter().map(|order_ref| async move {
+            let outcome = match self.cancel(order_ref).await {
+                Ok(()) => CancelOutcome::Cancelled,
+                // BankID answers invalidParameters for orders that no longer exist.
+                Err(Error::ClientError {
+                    error:
+                        ClientError {
+                            error_code: ErrorCode::InvalidParameters,
+                            ..
+                        },
+                    ..
+                }) => CancelOutcome::AlreadyFinished,
+                Err(error) => CancelOutcome::Failed(error),
+            };
+
+            (order_ref, outcome)
+        }))
+        .await
+    }
+
+    /// Collects an order according to `policy`, yielding a response each time the hint code
+    /// changes.
+    ///
+    /// The stream ends after the order completes or fails, or after the first error. Passing the
+    /// policy's max duration cancels the order and yields `Error::DeadlineExceeded`.
+    pub fn collect_stream(
+        &self,
+        order_ref: OrderRef,
+        policy: PollPolicy,
+    ) -> impl Stream<Item = Result<CollectResponse, Error>> + '_ {
+        struct State {
+            machine: CollectStateMachine,
+            next_collect: Option<Duration>,
+            hint_codes: Vec<ObservedHintCode>,
+            started: Instant,
+            done: bool,
+        }
+
+        let state = State {
+            machine: CollectStateMachine::new(order_ref, policy),
+            next_collect: None,
+            hint_codes: Vec::new(),
+            started: Instant::now(),
+            done: false,
+        };
+
+        stream::unfold(state, move |mut state| async move {
+            if state.done {
+                return None;
+            }
+
+            loop {
+                if let Some(interval) = state.next_collect {
+                    tokio::time::sleep(interval).await;
+                }
+
+                if matches!(policy.deadline(), Some(deadline) if state.started.elapsed() >= deadline)
+                {
+                    state.done = true;
+
+                    let hint_codes = std::mem::take(&mut state.hint_codes);
+
+                    return Some((
+                        Err(self
+                            .give_up(order_ref, hint_codes, Default::default())
+                            .await),
+                        state,
+                    ));
+                }
+
+                let response = match self.collect(order_ref).await {
+                    Ok(response) => response,
+                    Err(error) => {
+                        state.done = true;
+
+                        return Some((Err(error), state));
+                    }
+                };
+
+                let response = match state.machine.apply(response) {
+                    Transition::Pending {
+                        changed: false,
+                        next_collect,
+                        ..
+                    } => {
+                        state.next_collect = Some(next_collect);
+                        continue;
+                    }
+                    Transition::Pending {
+                        hint_code,
+                        next_collect,
+                        ..
+                    } => {
+                        state.next_collect = Some(next_collect);
+                        state
+                            .hint_codes
+                            .push(ObservedHintCode::now(hint_code.to_owned()));
+
+                        CollectResponse::Pending {
+                            hint_code,
+                            order_ref,
+                        }
+                    }
+                    Transition::Failed { hint_code, .. } => CollectResponse::Failed {
+                        hint_code,
+                        order_ref,
+                    },
+                    Transition::Complete { completion_data } => CollectResponse::Complete {
+                        completion_data,
+                        order_ref,
+                    },
+                };
+
+                state.done = state.machine.is_finished();
+
+                return Some((Ok(response), state));
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::PollPolicy;
+    use crate::response::CollectHintCode;
+
+    #[test]
+    fn test_poll_policy_backoff() {
+        let policy = PollPolicy::new(Duration::from_secs(2))
+            .outstanding_transaction_backoff(2.0, Duration::from_secs(5));
+
+        let outstanding = CollectHintCode::OutstandingTransaction;
+
+        let interval = policy.next_interval(policy.interval(), &outstanding);
+        assert_eq!(interval, Duration::from_secs(4));
+
+        let interval = policy.next_interval(interval, &outstanding);
+        assert_eq!(interval, Duration::from_secs(5));
+
+        let interval = policy.next_interval(interval, &CollectHintCode::UserSign);
+        assert_eq!(interval, Duration::from_secs(2));
+
+        let policy = PollPolicy::default();
+        assert_eq!(
+            policy.next_interval(policy.interval(), &outstanding),
+            Duration::from_secs(2)
+        );
+    }
+
+    #[test]
+    fn test_poll_policy_or_deadline() {
+        let policy = PollPolicy::default();
+        assert_eq!(policy.or_deadline(None).deadline(), None);
+        assert_eq!(
+            policy.or_deadline(Some(Duration::from_secs(60))).deadline(),
+            Some(Duration::from_secs(60))
+        );
+
+        let policy = policy.max_duration(Duration::from_secs(30));
+        assert_eq!(
+            policy.or_deadline(Some(Duration::from_secs(60))).deadline(),
+            Some(Duration::from_secs(30))
+        );
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn test_auth_deadline() {
+        use crate::golden::{COLLECT_PENDING, ORDER_RESPONSE};
+        use crate::mock::{auth_request, MockRp};
+        use crate::{ApiPath, ClientBuilder};
+
+        let rp = MockRp::start().await;
+        rp.respond(ApiPath::Auth, ORDER_RESPONSE).await;
+        rp.respond(ApiPath::Collect, COLLECT_PENDING).await;
+        rp.respond(ApiPath::Cancel, "{}").await;
+
+        let client = ClientBuilder::new(rp.endpoint())
+            .auth_deadline(Duration::from_millis(50))
+            .sign_deadline(Duration::from_secs(600))
+            .build();
+
+        let error = client
+            .authenticate(
+                auth_request(None),
+                PollPolicy::new(Duration::from_millis(5)),
+            )
+            .await
+            .expect_err("Authenticate succeeded");
+
+        assert!(error.is_timeout());
+        assert_eq!(
+            error
+                .hint_codes()
+                .first()
+                .map(|observed| &observed.hint_code),
+            Some(&CollectHintCode::OutstandingTransaction)
+        );
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn test_hint_code_history() {
+        use crate::golden::{COLLECT_COMPLETE, COLLECT_FAILED, COLLECT_PENDING, ORDER_RESPONSE};
+        use crate::mock::{auth_request, MockRp};
+        use crate::{ApiPath, Client};
+
+        const COLLECT_USER_SIGN: &str = r#"{
+            "orderRef": "131daac9-16c6-4618-beb0-365768f37288",
+            "status": "pending",
+            "hintCode": "userSign"
+        }"#;
+
+        let policy = PollPolicy::new(Duration::from_millis(1));
+
+        let rp = MockRp::start().await;
+        rp.respond(ApiPath::Auth, ORDER_RESPONSE).await;
+        rp.respond_once(ApiPath::Collect, COLLECT_PENDING).await;
+        rp.respond_once(ApiPath::Collect, COLLECT_PENDING).await;
+        rp.respond_once(ApiPath::Collect, COLLECT_USER_SIGN).await;
+        rp.respond(ApiPath::Collect, COLLECT_COMPLETE).await;
+
+        let completion = Client::new(rp.endpoint())
+            .authenticate(auth_request(None), policy)
+            .await
+            .expect("Authenticate failed");
+
+        let hint_codes: Vec<_> = completion
+            .hint_codes
+            .iter()
+            .map(|observed| observed.hint_code.to_owned())
+            .collect();
+        assert_eq!(
+            hint_codes,
+            vec![
+                CollectHintCode::OutstandingTransaction,
+                CollectHintCode::UserSign
+            ]
+        );
+        assert!(completion.hint_codes[0].at <= completion.hint_codes[1].at);
+
+        let rp = MockRp::start().await;
+        rp.respond(ApiPath::Auth, ORDER_RESPONSE).await;
+        rp.respond_once(ApiPath::Collect, COLLECT_PENDING).await;
+        rp.respond(ApiPath::Collect, COLLECT_FAILED).await;
+
+        let error = Client::new(rp.endpoint())
+            .authenticate(auth_request(None), policy)
+            .await
+            .expect_err("Authenticate succeeded");
+
+        assert!(error.is_user_cancelled());
+        assert_eq!(
+            error
+                .hint_codes()
+                .iter()
+                .map(|observed| observed.hint_code.to_owned())
+                .collect::<Vec<_>>(),
+            vec![
+                CollectHintCode::OutstandingTransaction,
+                CollectHintCode::UserCancel
+            ]
+        );
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn test_phase_events() {
+        use std::sync::{Arc, Mutex};
+
+        use crate::golden::{COLLECT_COMPLETE, ORDER_RESPONSE};
+        use crate::mock::{auth_request, MockRp};
+        use crate::telemetry::{MetricsHook, Phase, PhaseEvent, RequestEvent};
+        use crate::{ApiPath, ClientBuilder};
+
+        struct Phases(Arc<Mutex<Vec<Phase>>>);
+
+        impl MetricsHook for Phases {
+            fn record(&self, _event: &RequestEvent) {}
+
+            fn record_phase(&self, event: &PhaseEvent) {
+                self.0.lock().expect("Poisoned").push(event.phase);
+            }
+        }
+
+        let rp = MockRp::start().await;
+        rp.respond(ApiPath::Auth, ORDER_RESPONSE).await;
+        for hint_code in ["outstandingTransaction", "started", "userSign"] {
+            let body = format!(
+                r#"{{"orderRef": "131daac9-16c6-4618-beb0-365768f37288", "status": "pending", "hintCode": "{}"}}"#,
+                hint_code
+            );
+            rp.respond_once(ApiPath::Collect, &body).await;
+        }
+        rp.respond(ApiPath::Collect, COLLECT_COMPLETE).await;
+
+        let phases = Arc::new(Mutex::new(Vec::new()));
+        let client = ClientBuilder::new(rp.endpoint())
+            .metrics_hook(Phases(phases.to_owned()))
+            .build();
+
+        client
+            .authenticate(
+                auth_request(None),
+                PollPolicy::new(Duration::from_millis(1)),
+            )
+            .await
+            .expect("Authenticate failed");
+
+        assert_eq!(
+            *phases.lock().expect("Poisoned"),
+            vec![
+                Phase::CreateOrder,
+                Phase::StartedToUserSign,
+                Phase::Complete
+            ]
+        );
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn test_cancel_reason() {
+        use std::sync::{Arc, Mutex};
+
+        use super::CancelReason;
+        use crate::mock::MockRp;
+        use crate::telemetry::{MetricsHook, RequestEvent};
+        use crate::{ApiPath, ClientBuilder, Uuid};
+
+        struct Reasons(Arc<Mutex<Vec<Option<CancelReason>>>>);
+
+        impl MetricsHook for Reasons {
+            fn record(&self, event: &RequestEvent) {
+                self.0
+                    .lock()
+                    .expect("Poisoned")
+                    .push(event.cancel_reason.to_owned());
+            }
+        }
+
+        let rp = MockRp::start().await;
+        rp.respond(ApiPath::Cancel, "{}").await;
+
+        let reasons = Arc::new(Mutex::new(Vec::new()));
+        let client = ClientBuilder::new(rp.endpoint())
+            .metrics_hook(Reasons(reasons.to_owned()))
+            .build();
+
+        client
+            .cancel(Uuid::nil().into())
+            .await
+            .expect("Cancel failed");
+        client
+            .cancel_with_reason(Uuid::nil().into(), CancelReason::NavigatedAway)
+            .await
+            .expect("Cancel failed");
+
+        assert_eq!(
+            *reasons.lock().expect("Poisoned"),
+            vec![None, Some(CancelReason::NavigatedAway)]
+        );
+    }
+}
@@ -0,0 +1,51 @@
+//! JSON fixtures of RP API responses, and a helper to check that models survive a round trip
+//! through them.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+pub const ORDER_RESPONSE: &str = include_str!("./fixtures/order_response.json");
+pub const COLLECT_PENDING: &str = include_str!("./fixtures/collect_pending.json");
+pub const COLLECT_FAILED: &str = include_str!("./fixtures/collect_failed.json");
+pub const COLLECT_COMPLETE: &str = include_str!("./fixtures/collect_complete.json");
+pub const CLIENT_ERROR: &str = include_str!("./fixtures/client_error.json");
+
+/// Deserializes `fixture` into `T` and asserts that serializing it again gives back the same
+/// JSON, ignoring formatting and key order.
+///
+/// # Panics
+///
+/// If the fixture doesn't deserialize into `T`, or the round trip changes the JSON.
+#[allow(clippy::expect_used)]
+pub fn assert_roundtrip<T>(fixture: &str) -> T
+where
+    T: Serialize + DeserializeOwned,
+{
+    let expected: Value = serde_json::from_str(fixture).expect("Fixture isn't valid JSON");
+    let value: T = serde_json::from_str(fixture).expect("Failed to deserialize fixture");
+    let actual = serde_json::to_value(&value).expect("Failed to serialize value");
+
+    assert_eq!(actual, expected, "Round trip changed the JSON");
+
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        assert_roundtrip, CLIENT_ERROR, COLLECT_COMPLETE, COLLECT_FAILED, COLLECT_PENDING,
+        ORDER_RESPONSE,
+    };
+    use crate::response::{ClientError, CollectResponse, OrderResponse};
+
+    #[test]
+    fn test_fixtures_roundtrip() {
+        assert_roundtrip::<OrderResponse>(ORDER_RESPONSE);
+        assert_roundtrip::<ClientError>(CLIENT_ERROR);
+
+        for fixture in [COLLECT_PENDING, COLLECT_FAILED, COLLECT_COMPLETE] {
+            assert_roundtrip::<CollectResponse>(fixture);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::time::{Duration, Instant};
+
+use crate::flow::{CancelReason, Completion, PollPolicy};
+use crate::qr::QrGenerator;
+use crate::request::{AuthRequest, SignRequest};
+use crate::response::{CollectResponse, OrderResponse};
+use crate::telemetry::RequestContext;
+use crate::{Client, Error, Metadata, OrderRef};
+
+/// A started auth or sign order, tied to the client that started it.
+#[derive(Debug)]
+pub struct OrderHandle<'a> {
+    client: &'a Client,
+    order: OrderResponse,
+    qr_generator: QrGenerator,
+    metadata: Option<Metadata>,
+    created: Instant,
+}
+
+impl<'a> OrderHandle<'a> {
+    fn new(client: &'a Client, order: OrderResponse, metadata: Option<Metadata>) -> Self {
+        let created = Instant::now();
+
+        OrderHandle {
+            client,
+            qr_generator: QrGenerator::new(&order, created),
+            order,
+            metadata,
+            created,
+        }
+    }
+
+    fn context(&self) -> RequestContext<'_> {
+        RequestContext {
+            metadata: self.metadata.as_ref(),
+            ..Default::default()
+        }
+    }
+
+    pub fn order_ref(&self) -> OrderRef {
+        self.order.order_ref
+    }
+
+    pub fn order(&self) -> &OrderResponse {
+        &self.order
+    }
+
+    pub fn metadata(&self) -> Option<&Metadata> {
+        self.metadata.as_ref()
+    }
+
+    pub async fn collect(&self) -> Result<CollectResponse, Error> {
+        self.client
+            .collect_in(self.order.order_ref, self.context())
+            .await
+    }
+
+    pub async fn cancel(self) -> Result<(), Error> {
+        self.client
+            .cancel_in(self.order.order_ref, self.context())
+            .await
+    }
+
+    /// Cancels the order, reporting `reason` to the client's metrics hook.
+    pub async fn cancel_with_reason(self, reason: CancelReason) -> Result<(), Error> {
+        let context = RequestContext {
+            cancel_reason: Some(&reason),
+            ..self.context()
+        };
+
+        self.client.cancel_in(self.order.order_ref, context).await
+    }
+
+    /// Collects the order according to `policy` until it completes or fails.
+    ///
+    /// How long the user took from `started` to `userSign` and the order took to complete, counted
+    /// from when the handle was created, are reported to the client's metrics hook.
+    pub async fn wait(self, policy: PollPolicy) -> Result<Completion, Error> {
+        self.client
+            .wait_for_completion(
+                self.order.order_ref,
+                policy,
+                self.created,
+                self.metadata.as_ref(),
+            )
+            .await
+    }
+
+    /// The animated QR code payload for the given time since the order was started.
+    pub fn qr_payload(&self, elapsed: Duration) -> String {
+        self.qr_generator.payload(elapsed)
+    }
+}
+
+impl Client {
+    pub async fn auth_handle(&self, request: AuthRequest) -> Result<OrderHandle<'_>, Error> {
+        let order = self.auth(request).await?;
+
+        Ok(OrderHandle::new(self, order, None))
+    }
+
+    pub async fn sign_handle(&self, request: SignRequest) -> Result<OrderHandle<'_>, Error> {
+        let order = self.sign(request).await?;
+
+        Ok(OrderHandle::new(self, order, None))
+    }
+
+    /// Starts an auth order with `metadata`, which is passed on to the metrics events of every
+    /// request for the order.
+    pub async fn auth_handle_with_metadata(
+        &self,
+        request: AuthRequest,
+        metadata: Metadata,
+    ) -> Result<OrderHandle<'_>, Error> {
+        let context = RequestContext {
+            metadata: Some(&metadata),
+            ..Default::default()
+        };
+        let order = self
+            .send_retrying(self.auth_request(request)?, &[], context)
+            .await?
+            .body;
+
+        Ok(OrderHandle::new(self, order, Some(metadata)))
+    }
+
+    /// Starts a sign order with `metadata`, see `auth_handle_with_metadata`.
+    pub async fn sign_handle_with_metadata(
+        &self,
+        request: SignRequest,
+        metadata: Metadata,
+    ) -> Result<OrderHandle<'_>, Error> {
+        let context = RequestContext {
+            metadata: Some(&metadata),
+            ..Default::default()
+        };
+        let order = self
+            .send_retrying(self.sign_request(request)?, &[], context)
+            .await?
+            .body;
+
+        Ok(OrderHandle::new(self, order, Some(metadata)))
+    }
+}
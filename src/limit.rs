@@ -0,0 +1,84 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+
+/// Limits the requests a client has in flight and how often it starts new ones.
+#[derive(Debug, Default)]
+pub(crate) struct Limiter {
+    in_flight: Option<Arc<Semaphore>>,
+    // The spacing between requests and when the next one may start.
+    rate: Option<(Duration, Mutex<Instant>)>,
+}
+
+impl Limiter {
+    pub(crate) fn new(max_in_flight: Option<usize>, max_per_second: Option<u32>) -> Limiter {
+        Limiter {
+            in_flight: max_in_flight.map(|max| Arc::new(Semaphore::new(max.max(1)))),
+            rate: max_per_second.map(|max| {
+                (
+                    Duration::from_secs(1) / max.max(1),
+                    Mutex::new(Instant::now()),
+                )
+            }),
+        }
+    }
+
+    /// Waits until a request may be sent, the returned permit must be held until it's done.
+    pub(crate) async fn acquire(&self) -> Option<OwnedSemaphorePermit> {
+        let permit = match &self.in_flight {
+            Some(semaphore) => semaphore.to_owned().acquire_owned().await.ok(),
+            None => None,
+        };
+
+        if let Some((spacing, next)) = &self.rate {
+            let start = {
+                let mut next = next.lock().await;
+                let start = (*next).max(Instant::now());
+                *next = start + *spacing;
+                start
+            };
+
+            tokio::time::sleep_until(start).await;
+        }
+
+        permit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::Instant;
+
+    use super::Limiter;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_limiter_rate() {
+        let limiter = Limiter::new(None, Some(10));
+        let started = Instant::now();
+
+        for _ in 0..5 {
+            limiter.acquire().await;
+        }
+
+        assert_eq!(started.elapsed(), Duration::from_millis(400));
+    }
+
+    #[tokio::test]
+    async fn test_limiter_in_flight() {
+        let limiter = Limiter::new(Some(1), None);
+
+        let permit = limiter.acquire().await;
+        assert!(
+            tokio::time::timeout(Duration::from_millis(10), limiter.acquire())
+                .await
+                .is_err()
+        );
+
+        drop(permit);
+        assert!(limiter.acquire().await.is_some());
+    }
+}
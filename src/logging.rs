@@ -0,0 +1,138 @@
+use std::fmt;
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use crate::{ApiPath, ClientBuilder};
+
+/// Fields that are replaced before a request body is handed to a logger.
+const SENSITIVE_FIELDS: [&str; 3] = ["personalNumber", "userVisibleData", "userNonVisibleData"];
+
+const REDACTED: &str = "<redacted>";
+
+/// The `log` target of events a service should keep, e.g. a client switch.
+pub const TARGET: &str = "bankid";
+
+type LogFn = dyn Fn(ApiPath, &Value) + Send + Sync;
+
+#[derive(Clone)]
+pub(crate) struct RequestLogger(Arc<LogFn>);
+
+impl RequestLogger {
+    pub(crate) fn log(&self, path: ApiPath, mut body: Value) {
+        sanitize(&mut body);
+
+        (self.0)(path, &body)
+    }
+}
+
+impl fmt::Debug for RequestLogger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RequestLogger")
+    }
+}
+
+/// Replaces personal numbers and user visible and non-visible data anywhere in `body`.
+pub fn sanitize(body: &mut Value) {
+    match body {
+        Value::Object(fields) => {
+            for (name, value) in fields.iter_mut() {
+                if SENSITIVE_FIELDS.contains(&name.as_str()) && !value.is_null() {
+                    *value = Value::String(REDACTED.to_owned());
+                } else {
+                    sanitize(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(sanitize),
+        _ => (),
+    }
+}
+
+impl ClientBuilder {
+    /// Calls `logger` with the JSON body of every outgoing request, for debugging the contract
+    /// with BankID.
+    ///
+    /// Bodies are sanitized first, personal numbers and user visible and non-visible data never
+    /// reach the logger.
+    pub fn log_request_bodies<F>(mut self, logger: F) -> ClientBuilder
+    where
+        F: Fn(ApiPath, &Value) + Send + Sync + 'static,
+    {
+        self.request_logger = Some(RequestLogger(Arc::new(logger)));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::sanitize;
+
+    #[test]
+    fn test_sanitize() {
+        let mut body = json!({
+            "endUserIp": "192.168.0.1",
+            "userVisibleData": "VHJhbnNmZXI=",
+            "userNonVisibleData": null,
+            "requirement": {
+                "personalNumber": "199001019802",
+                "certificatePolicies": ["1.2.3.4.5"]
+            }
+        });
+
+        sanitize(&mut body);
+
+        assert_eq!(
+            body,
+            json!({
+                "endUserIp": "192.168.0.1",
+                "userVisibleData": "<redacted>",
+                "userNonVisibleData": null,
+                "requirement": {
+                    "personalNumber": "<redacted>",
+                    "certificatePolicies": ["1.2.3.4.5"]
+                }
+            })
+        );
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn test_log_request_bodies() {
+        use std::sync::{Arc, Mutex};
+
+        use crate::golden::ORDER_RESPONSE;
+        use crate::mock::{auth_request, MockRp};
+        use crate::{testenv, ApiPath, ClientBuilder};
+
+        let rp = MockRp::start().await;
+        rp.respond(ApiPath::Auth, ORDER_RESPONSE).await;
+
+        let logged = Arc::new(Mutex::new(Vec::new()));
+        let client = {
+            let logged = logged.to_owned();
+
+            ClientBuilder::new(rp.endpoint())
+                .log_request_bodies(move |path, body| {
+                    logged
+                        .lock()
+                        .expect("Poisoned")
+                        .push((path, body.to_owned()))
+                })
+                .build()
+        };
+
+        client
+            .auth(auth_request(Some(testenv::DEMO_PERSONAL_NUMBERS[0])))
+            .await
+            .expect("Auth failed");
+
+        let logged = logged.lock().expect("Poisoned");
+        assert_eq!(logged.len(), 1);
+        assert_eq!(logged[0].0, ApiPath::Auth);
+        assert_eq!(logged[0].1["personalNumber"], "<redacted>");
+        assert_eq!(logged[0].1["endUserIp"], "127.0.0.1");
+    }
+}
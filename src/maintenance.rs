@@ -0,0 +1,107 @@
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::Client;
+
+/// A period during which BankID answered with `maintenance` errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaintenanceWindow {
+    pub first_seen: SystemTime,
+    pub last_seen: SystemTime,
+    pub count: u64,
+    /// When a request first succeeded after the window, `None` while maintenance is ongoing.
+    pub ended: Option<SystemTime>,
+}
+
+impl MaintenanceWindow {
+    pub fn is_ongoing(&self) -> bool {
+        self.ended.is_none()
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct MaintenanceTracker {
+    window: Mutex<Option<MaintenanceWindow>>,
+}
+
+impl MaintenanceTracker {
+    fn window(&self) -> std::sync::MutexGuard<'_, Option<MaintenanceWindow>> {
+        // The window is plain data, so it's still usable if a holder panicked.
+        self.window
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub(crate) fn record_maintenance(&self, now: SystemTime) {
+        let mut window = self.window();
+
+        match window.as_mut() {
+            Some(window) if window.is_ongoing() => {
+                window.last_seen = now;
+                window.count += 1;
+            }
+            _ => {
+                *window = Some(MaintenanceWindow {
+                    first_seen: now,
+                    last_seen: now,
+                    count: 1,
+                    ended: None,
+                })
+            }
+        }
+    }
+
+    pub(crate) fn record_success(&self, now: SystemTime) {
+        if let Some(window) = self.window().as_mut() {
+            if window.is_ongoing() {
+                window.ended = Some(now);
+            }
+        }
+    }
+
+    pub(crate) fn current(&self) -> Option<MaintenanceWindow> {
+        *self.window()
+    }
+}
+
+impl Client {
+    /// The most recent maintenance window seen by this client, if any.
+    pub fn maintenance_status(&self) -> Option<MaintenanceWindow> {
+        self.maintenance.current()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::MaintenanceTracker;
+
+    #[test]
+    fn test_maintenance_tracker() {
+        let tracker = MaintenanceTracker::default();
+        let start = SystemTime::UNIX_EPOCH;
+
+        tracker.record_success(start);
+        assert!(tracker.current().is_none());
+
+        tracker.record_maintenance(start);
+        tracker.record_maintenance(start + Duration::from_secs(10));
+
+        let window = tracker.current().expect("Expected a window");
+        assert!(window.is_ongoing());
+        assert_eq!(window.count, 2);
+        assert_eq!(window.last_seen, start + Duration::from_secs(10));
+
+        tracker.record_success(start + Duration::from_secs(20));
+        assert_eq!(
+            tracker.current().and_then(|window| window.ended),
+            Some(start + Duration::from_secs(20))
+        );
+
+        tracker.record_maintenance(start + Duration::from_secs(30));
+        let window = tracker.current().expect("Expected a window");
+        assert_eq!(window.count, 1);
+        assert_eq!(window.first_seen, start + Duration::from_secs(30));
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::Error;
+
+/// Caller data attached to an order, e.g. an internal user or transaction id.
+///
+/// It's never sent to BankID, only passed on to metrics events and records.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Metadata(BTreeMap<String, Value>);
+
+impl Metadata {
+    pub fn new() -> Metadata {
+        Metadata::default()
+    }
+
+    pub fn insert<K, V>(mut self, key: K, value: V) -> Result<Metadata, Error>
+    where
+        K: Into<String>,
+        V: Serialize,
+    {
+        self.0.insert(key.into(), serde_json::to_value(value)?);
+        Ok(self)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.0.get(key)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.0.iter()
+    }
+
+    /// The metadata as a JSON object, for storing in a single column.
+    pub fn to_json(&self) -> String {
+        Value::from(serde_json::Map::from_iter(self.0.to_owned())).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::Metadata;
+
+    #[test]
+    fn test_metadata() {
+        let metadata = Metadata::new()
+            .insert("userId", 42)
+            .and_then(|metadata| metadata.insert("transaction", "tx-1"))
+            .expect("Failed to insert metadata");
+
+        assert_eq!(metadata.get("userId"), Some(&json!(42)));
+        assert_eq!(metadata.to_json(), r#"{"transaction":"tx-1","userId":42}"#);
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn test_order_metadata() {
+        use std::sync::{Arc, Mutex};
+
+        use crate::golden::{COLLECT_PENDING, ORDER_RESPONSE};
+        use crate::mock::{auth_request, MockRp};
+        use crate::telemetry::{MetricsHook, RequestEvent};
+        use crate::{ApiPath, ClientBuilder};
+
+        struct Events(Arc<Mutex<Vec<RequestEvent>>>);
+
+        impl MetricsHook for Events {
+            fn record(&self, event: &RequestEvent) {
+                self.0.lock().expect("Poisoned").push(event.to_owned());
+            }
+        }
+
+        let rp = MockRp::start().await;
+        rp.respond(ApiPath::Auth, ORDER_RESPONSE).await;
+        rp.respond(ApiPath::Collect, COLLECT_PENDING).await;
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let client = ClientBuilder::new(rp.endpoint())
+            .metrics_hook(Events(events.to_owned()))
+            .build();
+        let metadata = Metadata::new()
+            .insert("userId", 42)
+            .expect("Failed to insert metadata");
+
+        let handle = client
+            .auth_handle_with_metadata(auth_request(None), metadata.to_owned())
+            .await
+            .expect("Auth failed");
+        handle.collect().await.expect("Collect failed");
+
+        let events = events.lock().expect("Poisoned");
+        assert_eq!(events.len(), 2);
+        assert!(events
+            .iter()
+            .all(|event| event.metadata.as_ref() == Some(&metadata)));
+    }
+}
@@ -0,0 +1,59 @@
+use std::fmt;
+use std::sync::Arc;
+
+use reqwest_middleware::{ClientWithMiddleware, Middleware};
+
+use crate::{ClientBuilder, Error};
+
+#[derive(Clone, Default)]
+pub(crate) struct MiddlewareStack(Vec<Arc<dyn Middleware>>);
+
+impl MiddlewareStack {
+    pub(crate) fn build(&self, client: &reqwest::Client) -> Option<ClientWithMiddleware> {
+        if self.0.is_empty() {
+            return None;
+        }
+
+        Some(
+            self.0
+                .iter()
+                .fold(
+                    reqwest_middleware::ClientBuilder::new(client.to_owned()),
+                    |builder, middleware| builder.with_arc(middleware.to_owned()),
+                )
+                .build(),
+        )
+    }
+}
+
+impl fmt::Debug for MiddlewareStack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "MiddlewareStack({} middleware)", self.0.len())
+    }
+}
+
+impl ClientBuilder {
+    /// Adds a `reqwest-middleware` middleware, run in the order added for every request.
+    #[cfg_attr(docsrs, doc(cfg(feature = "middleware")))]
+    pub fn with_middleware<M>(self, middleware: M) -> ClientBuilder
+    where
+        M: Middleware,
+    {
+        self.with_arc_middleware(Arc::new(middleware))
+    }
+
+    #[cfg_attr(docsrs, doc(cfg(feature = "middleware")))]
+    pub fn with_arc_middleware(mut self, middleware: Arc<dyn Middleware>) -> ClientBuilder {
+        self.middleware.0.push(middleware);
+        self
+    }
+}
+
+impl From<reqwest_middleware::Error> for Error {
+    fn from(inner: reqwest_middleware::Error) -> Self {
+        match inner {
+            reqwest_middleware::Error::Reqwest(inner) => Self::ReqwestError(inner),
+            inner => Self::MiddlewareError(inner),
+        }
+    }
+}
@@ -0,0 +1,316 @@
+//! A local HTTP server emulating the RP API, for testing the full request and response path
+//! without network access.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use wiremock::matchers::{method, path_regex};
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+use crate::response::{ClientError, ErrorCode};
+use crate::{ApiPath, Endpoint};
+
+/// An emulated RP API, answering every API version.
+///
+/// Responses are matched in the order they were added, so a sequence of collect responses is
+/// scripted with `respond_once` followed by a final `respond`.
+#[derive(Debug)]
+pub struct MockRp {
+    server: MockServer,
+}
+
+impl MockRp {
+    pub async fn start() -> MockRp {
+        MockRp {
+            server: MockServer::start().await,
+        }
+    }
+
+    /// An endpoint for a `Client` talking to this server.
+    pub fn endpoint(&self) -> Endpoint {
+        Endpoint::Mock(format!("{}/rp/", self.server.uri()))
+    }
+
+    /// The underlying server, for expectations and inspecting received requests.
+    pub fn server(&self) -> &MockServer {
+        &self.server
+    }
+
+    /// Answer every request to `path` with the JSON `body`.
+    pub async fn respond(&self, path: ApiPath, body: &str) {
+        self.mock(path, ok(body)).mount(&self.server).await;
+    }
+
+    /// Answer the next request to `path` with the JSON `body`.
+    pub async fn respond_once(&self, path: ApiPath, body: &str) {
+        self.mock(path, ok(body))
+            .up_to_n_times(1)
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Answer every request to `path` with an error JSON body, as BankID does.
+    pub async fn respond_error(
+        &self,
+        path: ApiPath,
+        status: u16,
+        error_code: ErrorCode,
+        details: &str,
+    ) {
+        let error = ClientError {
+            error_code,
+            details: details.to_owned(),
+        };
+
+        self.mock(path, ResponseTemplate::new(status).set_body_json(error))
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Answer every request to `path` with the JSON `body`, slowed down and broken as described
+    /// by `faults`.
+    pub async fn respond_unreliably(&self, path: ApiPath, body: &str, faults: Faults) {
+        let responder = Unreliable {
+            body: body.to_owned(),
+            state: Mutex::new(faults.seed),
+            faults,
+        };
+
+        self.mock(path, responder).mount(&self.server).await;
+    }
+
+    fn mock<R: Respond + 'static>(&self, path: ApiPath, response: R) -> Mock {
+        Mock::given(method("POST"))
+            .and(path_regex(format!(r"^/rp/v\d+\.\d+/{}$", path)))
+            .respond_with(response)
+    }
+}
+
+/// Latency and errors for `MockRp::respond_unreliably`.
+///
+/// Faults are drawn from a generator seeded with `seed`, so a failing test can be replayed.
+#[derive(Debug, Clone)]
+pub struct Faults {
+    min_latency: Duration,
+    max_latency: Duration,
+    maintenance: f64,
+    timeout: f64,
+    timeout_delay: Duration,
+    malformed_json: f64,
+    seed: u64,
+}
+
+impl Default for Faults {
+    fn default() -> Self {
+        Faults {
+            min_latency: Duration::ZERO,
+            max_latency: Duration::ZERO,
+            maintenance: 0.0,
+            timeout: 0.0,
+            timeout_delay: Duration::from_secs(60),
+            malformed_json: 0.0,
+            seed: 1,
+        }
+    }
+}
+
+impl Faults {
+    pub fn new() -> Faults {
+        Faults::default()
+    }
+
+    /// Delay every response by a uniformly distributed time between `min` and `max`.
+    pub fn latency(mut self, min: Duration, max: Duration) -> Faults {
+        self.min_latency = min;
+        self.max_latency = max.max(min);
+        self
+    }
+
+    /// Answer with a 503 `maintenance` error with the given probability.
+    pub fn maintenance(mut self, probability: f64) -> Faults {
+        self.maintenance = probability;
+        self
+    }
+
+    /// Hold the response for `delay` with the given probability, for exceeding the client's
+    /// timeout.
+    pub fn timeout(mut self, probability: f64, delay: Duration) -> Faults {
+        self.timeout = probability;
+        self.timeout_delay = delay;
+        self
+    }
+
+    /// Answer with a body that isn't JSON with the given probability.
+    pub fn malformed_json(mut self, probability: f64) -> Faults {
+        self.malformed_json = probability;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Faults {
+        self.seed = seed;
+        self
+    }
+}
+
+struct Unreliable {
+    body: String,
+    faults: Faults,
+    state: Mutex<u64>,
+}
+
+impl Unreliable {
+    // A random number in [0, 1), from a xorshift generator.
+    fn next(&self) -> f64 {
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        *state = state.max(1);
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+
+        (*state >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl Respond for Unreliable {
+    fn respond(&self, _request: &Request) -> ResponseTemplate {
+        let faults = &self.faults;
+        let latency =
+            faults.min_latency + (faults.max_latency - faults.min_latency).mul_f64(self.next());
+        let fault = self.next();
+
+        let response = if fault < faults.maintenance {
+            let error = ClientError {
+                error_code: ErrorCode::Maintenance,
+                details: "Simulated maintenance".to_owned(),
+            };
+            ResponseTemplate::new(503).set_body_json(error)
+        } else if fault < faults.maintenance + faults.timeout {
+            return ok(&self.body).set_delay(faults.timeout_delay);
+        } else if fault < faults.maintenance + faults.timeout + faults.malformed_json {
+            ResponseTemplate::new(200).set_body_raw("{\"orderRef\":", "application/json")
+        } else {
+            ok(&self.body)
+        };
+
+        response.set_delay(latency)
+    }
+}
+
+/// An auth request from localhost, as the tests against a `MockRp` send.
+#[cfg(test)]
+pub(crate) fn auth_request(
+    personal_number: Option<crate::PersonalNumber>,
+) -> crate::request::AuthRequest {
+    crate::request::AuthRequest {
+        end_user_ip: std::net::IpAddr::from(std::net::Ipv4Addr::LOCALHOST).into(),
+        personal_number,
+        requirement: None,
+        user_visible_data: None,
+        user_visible_data_format: None,
+        return_url: None,
+        return_risk: None,
+    }
+}
+
+fn ok(body: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_raw(body.to_owned(), "application/json")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{auth_request, Faults, MockRp};
+    use crate::golden::{COLLECT_COMPLETE, COLLECT_PENDING, ORDER_RESPONSE};
+    use crate::response::{CollectResponse, ErrorCode};
+    use crate::retry::RetryPolicy;
+    use crate::{testenv, ApiPath, Client, ClientBuilder, Error, Uuid};
+
+    #[tokio::test]
+    async fn test_mock_rp() {
+        let rp = MockRp::start().await;
+        let client = Client::new(rp.endpoint());
+
+        rp.respond(ApiPath::Auth, ORDER_RESPONSE).await;
+        rp.respond_once(ApiPath::Collect, COLLECT_PENDING).await;
+        rp.respond(ApiPath::Collect, COLLECT_COMPLETE).await;
+        rp.respond_error(
+            ApiPath::Cancel,
+            400,
+            ErrorCode::InvalidParameters,
+            "No such order",
+        )
+        .await;
+
+        let order = client.auth(auth_request(None)).await.expect("Auth failed");
+
+        assert!(matches!(
+            client.collect(order.order_ref).await,
+            Ok(CollectResponse::Pending { .. })
+        ));
+
+        match client.collect(order.order_ref).await {
+            Ok(CollectResponse::Complete {
+                completion_data, ..
+            }) => assert_eq!(
+                completion_data.user.personal_number,
+                testenv::DEMO_PERSONAL_NUMBERS[0]
+            ),
+            other => panic!("Unexpected collect response {:?}", other),
+        }
+
+        match client.cancel(Uuid::nil().into()).await {
+            Err(Error::ClientError { status, error }) => {
+                assert_eq!(status.as_u16(), 400);
+                assert!(matches!(error.error_code, ErrorCode::InvalidParameters));
+            }
+            other => panic!("Unexpected cancel response {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_faults() {
+        let rp = MockRp::start().await;
+        rp.respond_unreliably(
+            ApiPath::Collect,
+            COLLECT_PENDING,
+            Faults::new()
+                .latency(Duration::from_millis(1), Duration::from_millis(5))
+                .maintenance(0.5)
+                .seed(7),
+        )
+        .await;
+        rp.respond_unreliably(ApiPath::Cancel, "{}", Faults::new().malformed_json(1.0))
+            .await;
+        rp.respond_unreliably(
+            ApiPath::Auth,
+            ORDER_RESPONSE,
+            Faults::new().timeout(1.0, Duration::from_secs(5)),
+        )
+        .await;
+
+        let client = ClientBuilder::new(rp.endpoint())
+            .timeout(Duration::from_millis(50))
+            .retry_policy(
+                RetryPolicy::new(10).backoff(Duration::from_millis(1), Duration::from_millis(1)),
+            )
+            .build();
+
+        for _ in 0..3 {
+            client
+                .collect(Uuid::nil().into())
+                .await
+                .expect("Collect failed");
+        }
+        let collects = rp.server().received_requests().await.unwrap_or_default();
+        assert!(collects.len() > 3);
+
+        assert!(matches!(
+            client.cancel(Uuid::nil().into()).await,
+            Err(Error::UnexpectedResponse { .. })
+        ));
+
+        let timed_out = client.auth(auth_request(None)).await;
+        assert!(timed_out.is_err_and(|err| err.is_timeout()));
+    }
+}
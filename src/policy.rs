@@ -0,0 +1,135 @@
+//! Checks that can stop an order before it's sent to BankID.
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::request::EndUserIp;
+use crate::{testenv, ApiPath, ClientBuilder, Environment, Error, PersonalNumber};
+
+/// An auth or sign order about to be sent.
+#[derive(Debug, Clone, Copy)]
+pub struct OutgoingOrder<'a> {
+    pub path: ApiPath,
+    pub environment: Environment,
+    pub end_user_ip: &'a EndUserIp,
+    pub personal_number: Option<&'a PersonalNumber>,
+}
+
+pub trait OrderPolicy: Send + Sync {
+    /// Returns the reason the order must not be sent, if any.
+    fn check(&self, order: &OutgoingOrder<'_>) -> Result<(), String>;
+}
+
+/// Only lets test personal numbers reach the test environment, and keeps them out of production.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EnvironmentGuard;
+
+impl OrderPolicy for EnvironmentGuard {
+    fn check(&self, order: &OutgoingOrder<'_>) -> Result<(), String> {
+        let personal_number = match order.personal_number {
+            Some(personal_number) => personal_number,
+            None => return Ok(()),
+        };
+
+        match (
+            order.environment,
+            testenv::is_test_personal_number(personal_number),
+        ) {
+            (Environment::Test, false) => Err(format!(
+                "{} isn't a test personal number",
+                personal_number.masked()
+            )),
+            (Environment::Production, true) => Err(format!(
+                "{} is a test personal number",
+                personal_number.masked()
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct SharedPolicy(Arc<dyn OrderPolicy>);
+
+impl SharedPolicy {
+    pub(crate) fn check(&self, order: &OutgoingOrder<'_>) -> Result<(), Error> {
+        self.0.check(order).map_err(Error::PolicyViolation)
+    }
+}
+
+impl fmt::Debug for SharedPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "OrderPolicy")
+    }
+}
+
+impl ClientBuilder {
+    /// Checks every auth and sign order with `policy` before it's sent, after the built-in
+    /// validation.
+    pub fn order_policy<P>(mut self, policy: P) -> ClientBuilder
+    where
+        P: OrderPolicy + 'static,
+    {
+        self.order_policy = Some(SharedPolicy(Arc::new(policy)));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::{EnvironmentGuard, OrderPolicy, OutgoingOrder};
+    use crate::request::EndUserIp;
+    use crate::testenv::DEMO_PERSONAL_NUMBERS;
+    use crate::{ApiPath, Environment, PersonalNumber};
+
+    #[test]
+    fn test_environment_guard() {
+        let end_user_ip = EndUserIp::from(IpAddr::V4(Ipv4Addr::new(194, 168, 2, 25)));
+        let real = PersonalNumber::parse("198710101232").expect("Parsing failed");
+
+        let check = |environment, personal_number| {
+            EnvironmentGuard.check(&OutgoingOrder {
+                path: ApiPath::Auth,
+                environment,
+                end_user_ip: &end_user_ip,
+                personal_number,
+            })
+        };
+
+        assert!(check(Environment::Test, Some(&DEMO_PERSONAL_NUMBERS[0])).is_ok());
+        assert!(check(Environment::Test, Some(&real)).is_err());
+        assert!(check(Environment::Production, Some(&real)).is_ok());
+        assert!(check(Environment::Production, Some(&DEMO_PERSONAL_NUMBERS[0])).is_err());
+        assert!(check(Environment::Test, None).is_ok());
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn test_order_policy() {
+        use crate::golden::ORDER_RESPONSE;
+        use crate::mock::{auth_request, MockRp};
+        use crate::{ClientBuilder, Error};
+
+        let rp = MockRp::start().await;
+        rp.respond(ApiPath::Auth, ORDER_RESPONSE).await;
+
+        let client = ClientBuilder::new(rp.endpoint())
+            .order_policy(EnvironmentGuard)
+            .build();
+        let auth = |personal_number| client.auth(auth_request(Some(personal_number)));
+
+        auth(DEMO_PERSONAL_NUMBERS[0]).await.expect("Auth failed");
+
+        let real = PersonalNumber::parse("198710101232").expect("Parsing failed");
+        assert!(matches!(auth(real).await, Err(Error::PolicyViolation(_))));
+        assert_eq!(
+            rp.server()
+                .received_requests()
+                .await
+                .map(|requests| requests.len()),
+            Some(1)
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::response::OrderResponse;
+#[cfg(feature = "qrcode")]
+use crate::Error;
+use crate::SecretString;
+
+/// Generates the animated QR code payload for an order.
+///
+/// The payload changes every second and has the form
+/// `bankid.<qrStartToken>.<seconds since order start>.<qrAuthCode>`.
+#[derive(Clone)]
+pub struct QrGenerator {
+    qr_start_token: String,
+    qr_start_secret: SecretString,
+    start_time: Instant,
+}
+
+impl fmt::Debug for QrGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QrGenerator")
+            .field("qr_start_token", &self.qr_start_token)
+            .field("qr_start_secret", &"<redacted>")
+            .field("start_time", &self.start_time)
+            .finish()
+    }
+}
+
+impl QrGenerator {
+    pub fn new(order: &OrderResponse, start_time: Instant) -> QrGenerator {
+        QrGenerator {
+            qr_start_token: order.qr_start_token.to_string(),
+            qr_start_secret: SecretString::from(order.qr_start_secret.to_string()),
+            start_time,
+        }
+    }
+
+    /// Payload for the given number of seconds since the order was started.
+    pub fn payload(&self, elapsed: Duration) -> String {
+        let time = elapsed.as_secs().to_string();
+
+        #[allow(clippy::expect_used)]
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.qr_start_secret.as_bytes())
+            .expect("HMAC can take a key of any size");
+        mac.update(time.as_bytes());
+
+        let qr_auth_code: String = mac
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        format!("bankid.{}.{}.{}", self.qr_start_token, time, qr_auth_code)
+    }
+
+    /// Payload for the current second.
+    pub fn current(&self) -> String {
+        self.payload(self.start_time.elapsed())
+    }
+
+    /// SVG image of the QR code for the given number of seconds since the order was started.
+    #[cfg(feature = "qrcode")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "qrcode")))]
+    pub fn svg(&self, elapsed: Duration) -> Result<String, Error> {
+        render_svg(&self.payload(elapsed))
+    }
+
+    /// PNG image of the QR code for the given number of seconds since the order was started.
+    #[cfg(feature = "qrcode")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "qrcode")))]
+    pub fn png(&self, elapsed: Duration) -> Result<Vec<u8>, Error> {
+        render_png(&self.payload(elapsed))
+    }
+}
+
+#[cfg(feature = "qrcode")]
+fn qr_code(payload: &str) -> Result<qrcode::QrCode, Error> {
+    qrcode::QrCode::new(payload.as_bytes()).map_err(|err| Error::QrCodeError(err.to_string()))
+}
+
+#[cfg(feature = "qrcode")]
+#[cfg_attr(docsrs, doc(cfg(feature = "qrcode")))]
+pub fn render_svg(payload: &str) -> Result<String, Error> {
+    Ok(qr_code(payload)?
+        .render::<qrcode::render::svg::Color<'_>>()
+        .min_dimensions(200, 200)
+        .build())
+}
+
+#[cfg(feature = "qrcode")]
+#[cfg_attr(docsrs, doc(cfg(feature = "qrcode")))]
+pub fn render_png(payload: &str) -> Result<Vec<u8>, Error> {
+    let image = qr_code(payload)?
+        .render::<image::Luma<u8>>()
+        .min_dimensions(200, 200)
+        .build();
+
+    let mut png = std::io::Cursor::new(Vec::new());
+
+    image
+        .write_to(&mut png, image::ImageFormat::Png)
+        .map_err(|err| Error::QrCodeError(err.to_string()))?;
+
+    Ok(png.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::QrGenerator;
+    use crate::response::OrderResponse;
+    use crate::Uuid;
+
+    fn order() -> OrderResponse {
+        OrderResponse {
+            order_ref: Uuid::nil().into(),
+            auto_start_token: Uuid::nil().into(),
+            qr_start_token: Uuid::parse_str("67df3917-fa0d-44e5-b327-edcc928297f8")
+                .map(Into::into)
+                .expect("Invalid uuid"),
+            qr_start_secret: Uuid::parse_str("d28db9a7-4cde-429e-a983-359be676944c")
+                .map(Into::into)
+                .expect("Invalid uuid"),
+        }
+    }
+
+    #[test]
+    fn test_qr_payload() {
+        let generator = QrGenerator::new(&order(), Instant::now());
+
+        // Test vectors from the BankID relying party guidelines.
+        for (seconds, qr_auth_code) in [
+            (
+                0,
+                "dc69358e712458a66a7525beef148ae8526b1c71610eff2c16cdffb4cdac9bf8",
+            ),
+            (
+                1,
+                "949d559bf23403952a94d103e67743126381eda00f0b3cbddbf7c96b1adcbce2",
+            ),
+            (
+                2,
+                "a9e5ec59cb4eee4ef4117150abc58fad7a85439a6a96ccbecc3668b41795b3f3",
+            ),
+        ] {
+            assert_eq!(
+                generator.payload(Duration::from_millis(seconds * 1000 + 500)),
+                format!(
+                    "bankid.67df3917-fa0d-44e5-b327-edcc928297f8.{}.{}",
+                    seconds, qr_auth_code
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_secret_redacted() {
+        let order = order();
+        let generator = QrGenerator::new(&order, Instant::now());
+
+        for debug in [format!("{:?}", order), format!("{:?}", generator)] {
+            assert!(!debug.contains("d28db9a7"));
+            assert!(debug.contains("67df3917"));
+        }
+    }
+
+    #[cfg(feature = "qrcode")]
+    #[test]
+    fn test_qr_images() {
+        let generator = QrGenerator::new(&order(), Instant::now());
+
+        let svg = generator.svg(Duration::ZERO).expect("Failed to render svg");
+        assert!(svg.starts_with("<?xml"));
+
+        let png = generator.png(Duration::ZERO).expect("Failed to render png");
+        assert_eq!(&png[1..4], b"PNG");
+    }
+}
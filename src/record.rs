@@ -0,0 +1,237 @@
+//! Flat versions of collect responses, for storing in a database.
+//!
+//! Every field is a string or an optional string, codes are stored with the names BankID uses.
+
+use serde::{Deserialize, Serialize};
+
+use crate::flow::CancelReason;
+use crate::response::{CollectResponse, CollectStatus, CompletionData};
+use crate::{Metadata, OrderRef};
+
+/// The state of an order after a collect.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct CollectRecord {
+    pub order_ref: String,
+    /// `pending`, `failed` or `complete`.
+    pub status: String,
+    pub hint_code: Option<String>,
+    /// The order's metadata as a JSON object, see `with_metadata`.
+    pub metadata: Option<String>,
+}
+
+/// The user and evidence of a completed order.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct CompletionRecord {
+    pub order_ref: String,
+    pub personal_number: String,
+    pub name: String,
+    pub given_name: String,
+    pub surname: String,
+    pub ip_address: String,
+    pub uhi: Option<String>,
+    pub cert_not_before: Option<String>,
+    pub cert_not_after: Option<String>,
+    pub signature: String,
+    pub ocsp_response: String,
+    /// `low`, `moderate` or `high`.
+    pub risk: Option<String>,
+    pub bank_id_issue_date: Option<String>,
+    /// The step-up as a JSON object, e.g. `{"mrtd":true}`.
+    pub step_up: Option<String>,
+    pub metadata: Option<String>,
+}
+
+/// A cancellation by the relying party.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct CancelRecord {
+    pub order_ref: String,
+    pub reason: String,
+    pub metadata: Option<String>,
+}
+
+impl CancelRecord {
+    pub fn new(order_ref: &OrderRef, reason: &CancelReason) -> CancelRecord {
+        CancelRecord {
+            order_ref: order_ref.to_string(),
+            reason: reason.to_string(),
+            metadata: None,
+        }
+    }
+
+    pub fn with_metadata(mut self, metadata: &Metadata) -> CancelRecord {
+        self.metadata = Some(metadata.to_json());
+        self
+    }
+}
+
+// The name a unit variant is serialized with, e.g. "userSign".
+fn serialized_name<T: Serialize>(value: &T) -> Option<String> {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => Some(name),
+        _ => None,
+    }
+}
+
+impl From<&CollectResponse> for CollectRecord {
+    fn from(response: &CollectResponse) -> Self {
+        let frontend = response.to_frontend();
+        let order_ref = match response {
+            CollectResponse::Pending { order_ref, .. }
+            | CollectResponse::Failed { order_ref, .. }
+            | CollectResponse::Complete { order_ref, .. } => order_ref,
+        };
+
+        CollectRecord {
+            order_ref: order_ref.to_string(),
+            status: serialized_name(&frontend.status).unwrap_or_default(),
+            hint_code: frontend.hint_code.as_ref().and_then(serialized_name),
+            metadata: None,
+        }
+    }
+}
+
+impl CollectRecord {
+    /// Attaches the order's metadata, so the record can be correlated with the business
+    /// transaction.
+    pub fn with_metadata(mut self, metadata: &Metadata) -> CollectRecord {
+        self.metadata = Some(metadata.to_json());
+        self
+    }
+}
+
+impl CompletionRecord {
+    pub fn new(order_ref: &OrderRef, completion_data: &CompletionData) -> CompletionRecord {
+        let user = &completion_data.user;
+        let cert = completion_data.cert.as_ref();
+
+        CompletionRecord {
+            order_ref: order_ref.to_string(),
+            personal_number: user.personal_number.to_string(),
+            name: user.name.to_owned(),
+            given_name: user.given_name.to_owned(),
+            surname: user.surname.to_owned(),
+            ip_address: completion_data.device.ip_address.to_string(),
+            uhi: completion_data.device.uhi.to_owned(),
+            cert_not_before: cert.map(|cert| cert.not_before.to_owned()),
+            cert_not_after: cert.map(|cert| cert.not_after.to_owned()),
+            signature: completion_data.signature.to_owned(),
+            ocsp_response: completion_data.ocsp_response.to_owned(),
+            risk: completion_data.risk.as_ref().and_then(serialized_name),
+            bank_id_issue_date: completion_data.bank_id_issue_date.to_owned(),
+            step_up: completion_data
+                .step_up
+                .as_ref()
+                .and_then(|step_up| serde_json::to_string(step_up).ok()),
+            metadata: None,
+        }
+    }
+
+    pub fn with_metadata(mut self, metadata: &Metadata) -> CompletionRecord {
+        self.metadata = Some(metadata.to_json());
+        self
+    }
+}
+
+impl CollectResponse {
+    pub fn to_record(&self) -> CollectRecord {
+        CollectRecord::from(self)
+    }
+
+    /// The completion record, if the order is complete.
+    pub fn to_completion_record(&self) -> Option<CompletionRecord> {
+        match self {
+            CollectResponse::Complete {
+                order_ref,
+                completion_data,
+            } => Some(CompletionRecord::new(order_ref, completion_data)),
+            _ => None,
+        }
+    }
+}
+
+impl CollectStatus {
+    /// Parses a status stored in a `CollectRecord`.
+    pub fn from_record(status: &str) -> Option<CollectStatus> {
+        serde_json::from_value(serde_json::Value::String(status.to_owned())).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CollectRecord, CompletionRecord};
+    use crate::golden::{COLLECT_COMPLETE, COLLECT_PENDING};
+    use crate::response::{CollectResponse, CollectStatus};
+    use crate::Metadata;
+
+    #[test]
+    fn test_records() {
+        let pending: CollectResponse =
+            serde_json::from_str(COLLECT_PENDING).expect("Failed to deserialize fixture");
+        let record = pending.to_record();
+
+        assert_eq!(record.status, "pending");
+        assert_eq!(record.hint_code.as_deref(), Some("outstandingTransaction"));
+        assert_eq!(
+            CollectStatus::from_record(&record.status),
+            Some(CollectStatus::Pending)
+        );
+        assert_eq!(pending.to_completion_record(), None);
+
+        let complete: CollectResponse =
+            serde_json::from_str(COLLECT_COMPLETE).expect("Failed to deserialize fixture");
+        let completion = complete
+            .to_completion_record()
+            .expect("Expected a completion record");
+
+        assert_eq!(
+            complete.to_record(),
+            CollectRecord {
+                order_ref: completion.order_ref.to_owned(),
+                status: "complete".to_owned(),
+                hint_code: None,
+                metadata: None,
+            }
+        );
+        assert_eq!(completion.personal_number, "199001019802");
+
+        let metadata = Metadata::new()
+            .insert("userId", 42)
+            .expect("Failed to insert metadata");
+        assert_eq!(
+            completion.with_metadata(&metadata).metadata.as_deref(),
+            Some(r#"{"userId":42}"#)
+        );
+    }
+
+    #[test]
+    fn test_completion_record_v6_fields() {
+        let mut json: serde_json::Value =
+            serde_json::from_str(COLLECT_COMPLETE).expect("Failed to deserialize fixture");
+        let completion_data = &mut json["completionData"];
+        completion_data["device"]["uhi"] = "OZvYM9VvyiAmG7NA5jU5zRGcTmi5qFdl".into();
+        completion_data["risk"] = "moderate".into();
+        completion_data["bankIdIssueDate"] = "2020-02-01".into();
+        completion_data["stepUp"] = serde_json::json!({ "mrtd": true });
+
+        let complete: CollectResponse =
+            serde_json::from_value(json).expect("Failed to deserialize response");
+        let record = complete
+            .to_completion_record()
+            .expect("Expected a completion record");
+
+        assert_eq!(
+            record.uhi.as_deref(),
+            Some("OZvYM9VvyiAmG7NA5jU5zRGcTmi5qFdl")
+        );
+        assert_eq!(record.risk.as_deref(), Some("moderate"));
+        assert_eq!(record.bank_id_issue_date.as_deref(), Some("2020-02-01"));
+        assert_eq!(record.step_up.as_deref(), Some(r#"{"mrtd":true}"#));
+
+        let stored = serde_json::to_string(&record).expect("Failed to serialize record");
+        assert_eq!(
+            serde_json::from_str::<CompletionRecord>(&stored)
+                .expect("Failed to deserialize record"),
+            record
+        );
+    }
+}
@@ -1,21 +1,231 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
 use serde::{Deserialize, Serialize};
 use std::net::IpAddr;
-use uuid::Uuid;
 
-use crate::PersonalNumber;
+use crate::{testenv, ApiVersion, Environment, Error, OrderRef, PersonalNumber};
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpSource {
+    /// Peer address of the socket the end user connected from.
+    Socket,
+    /// Address taken from `X-Forwarded-For`, where `hop` counts from the right.
+    ForwardedFor { hop: usize },
+}
+
+/// The IP address of the end user, together with where it was obtained.
+///
+/// BankID requires the relying party to send the address the end user actually
+/// connects from, so addresses that can't be routed on the internet are rejected
+/// when the production endpoint is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EndUserIp {
+    addr: IpAddr,
+    source: IpSource,
+}
+
+impl EndUserIp {
+    pub fn from_socket(addr: IpAddr) -> Self {
+        EndUserIp {
+            addr,
+            source: IpSource::Socket,
+        }
+    }
+
+    pub fn from_forwarded_for(addr: IpAddr, hop: usize) -> Self {
+        EndUserIp {
+            addr,
+            source: IpSource::ForwardedFor { hop },
+        }
+    }
+
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    pub fn source(&self) -> IpSource {
+        self.source
+    }
+
+    pub fn is_public(&self) -> bool {
+        match self.addr {
+            IpAddr::V4(addr) => {
+                let [a, b, c, _] = addr.octets();
+
+                !(addr.is_private()
+                    || addr.is_loopback()
+                    || addr.is_link_local()
+                    || addr.is_unspecified()
+                    || addr.is_broadcast()
+                    || addr.is_multicast()
+                    // "This network", 0.0.0.0/8
+                    || a == 0
+                    // IETF protocol assignments, 192.0.0.0/24
+                    || (a, b, c) == (192, 0, 0)
+                    // Carrier-grade NAT, 100.64.0.0/10
+                    || (a == 100 && (b & 0xc0) == 64)
+                    // Documentation, 192.0.2.0/24, 198.51.100.0/24 and 203.0.113.0/24
+                    || matches!((a, b, c), (192, 0, 2) | (198, 51, 100) | (203, 0, 113))
+                    // Benchmarking, 198.18.0.0/15
+                    || (a == 198 && (b & 0xfe) == 18)
+                    // Reserved, 240.0.0.0/4
+                    || (a & 0xf0) == 240)
+            }
+            IpAddr::V6(addr) => {
+                // IPv4-mapped, ::ffff:0:0/96
+                if let Some(mapped) = addr.to_ipv4_mapped() {
+                    return EndUserIp::from_socket(IpAddr::V4(mapped)).is_public();
+                }
+
+                let first_segment = addr.segments()[0];
+
+                !(addr.is_loopback()
+                    || addr.is_unspecified()
+                    || addr.is_multicast()
+                    // Unique local, fc00::/7
+                    || (first_segment & 0xfe00) == 0xfc00
+                    // Link local, fe80::/10
+                    || (first_segment & 0xffc0) == 0xfe80
+                    // Documentation, 2001:db8::/32
+                    || (first_segment == 0x2001 && addr.segments()[1] == 0x0db8))
+            }
+        }
+    }
+
+    pub(crate) fn validate_for_production(&self) -> Result<(), Error> {
+        if self.is_public() {
+            Ok(())
+        } else {
+            Err(Error::InvalidEndUserIp(
+                "Only public addresses are allowed in production",
+            ))
+        }
+    }
+}
+
+impl From<IpAddr> for EndUserIp {
+    fn from(addr: IpAddr) -> Self {
+        EndUserIp::from_socket(addr)
+    }
+}
+
+impl Serialize for EndUserIp {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.addr.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for EndUserIp {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        IpAddr::deserialize(deserializer).map(EndUserIp::from_socket)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
 #[serde(rename_all = "camelCase")]
 pub enum CardReaderClass {
     Class1,
     Class2,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Certificate policy OIDs used in production, as documented in the relying party guidelines. See
+/// `testenv::certificate_policies` for the test environment.
+pub mod certificate_policies {
+    pub const BANKID_ON_FILE: &str = "1.2.752.78.1.1";
+    pub const BANKID_ON_SMART_CARD: &str = "1.2.752.78.1.2";
+    pub const MOBILE_BANKID: &str = "1.2.752.78.1.5";
+    pub const NORDEA_EID_ON_FILE_AND_SMART_CARD: &str = "1.2.752.71.1.3";
+}
+
+/// A certificate policy, resolved to the OID used by the environment the order is sent to.
+///
+/// On its own a policy serializes to its production OID, the client replaces it with the test OID
+/// when talking to the test environment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CertificatePolicy {
+    BankIdOnFile,
+    BankIdOnSmartCard,
+    MobileBankId,
+    NordeaEidOnFileAndSmartCard,
+    Oid(String),
+}
+
+impl CertificatePolicy {
+    const NAMED: [CertificatePolicy; 4] = [
+        Self::BankIdOnFile,
+        Self::BankIdOnSmartCard,
+        Self::MobileBankId,
+        Self::NordeaEidOnFileAndSmartCard,
+    ];
+
+    pub fn oid(&self, environment: Environment) -> &str {
+        match (self, environment) {
+            (Self::BankIdOnFile, Environment::Production) => certificate_policies::BANKID_ON_FILE,
+            (Self::BankIdOnFile, Environment::Test) => {
+                testenv::certificate_policies::BANKID_ON_FILE
+            }
+            (Self::BankIdOnSmartCard, Environment::Production) => {
+                certificate_policies::BANKID_ON_SMART_CARD
+            }
+            (Self::BankIdOnSmartCard, Environment::Test) => {
+                testenv::certificate_policies::BANKID_ON_SMART_CARD
+            }
+            (Self::MobileBankId, Environment::Production) => certificate_policies::MOBILE_BANKID,
+            (Self::MobileBankId, Environment::Test) => testenv::certificate_policies::MOBILE_BANKID,
+            (Self::NordeaEidOnFileAndSmartCard, _) => {
+                certificate_policies::NORDEA_EID_ON_FILE_AND_SMART_CARD
+            }
+            (Self::Oid(oid), _) => oid,
+        }
+    }
+
+    pub fn from_oid(oid: &str) -> Self {
+        Self::NAMED
+            .into_iter()
+            .find(|policy| {
+                policy.oid(Environment::Production) == oid || policy.oid(Environment::Test) == oid
+            })
+            .unwrap_or_else(|| Self::Oid(oid.to_owned()))
+    }
+}
+
+impl Serialize for CertificatePolicy {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.oid(Environment::Production).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for CertificatePolicy {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        String::deserialize(deserializer).map(|oid| CertificatePolicy::from_oid(&oid))
+    }
+}
+
+/// Highest risk level BankID may assess for the order to go through, v6.0 only.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum RiskThreshold {
+    Low,
+    Moderate,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct Requirement {
     #[serde(skip_serializing_if = "Option::is_none")]
-    certificate_policies: Option<Vec<String>>,
+    certificate_policies: Option<Vec<CertificatePolicy>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     allow_fingerprint: Option<bool>,
@@ -28,24 +238,383 @@ pub struct Requirement {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     card_reader: Option<CardReaderClass>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    risk: Option<RiskThreshold>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pin_code: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mrtd: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    personal_number: Option<PersonalNumber>,
+}
+
+impl Requirement {
+    pub fn builder() -> RequirementBuilder {
+        RequirementBuilder::default()
+    }
+
+    pub(crate) fn certificate_policies(&self) -> Option<&[CertificatePolicy]> {
+        self.certificate_policies.as_deref()
+    }
+
+    pub(crate) fn personal_number(&self) -> Option<&PersonalNumber> {
+        self.personal_number.as_ref()
+    }
+
+    pub(crate) fn validate_for(&self, version: ApiVersion) -> Result<(), Error> {
+        if version == ApiVersion::V5_1 && self.risk.is_some() {
+            return Err(Error::InvalidRequirement(
+                "A risk threshold requires API version 6.0",
+            ));
+        }
+
+        if version == ApiVersion::V5_1 && self.pin_code.is_some() {
+            return Err(Error::InvalidRequirement(
+                "Requiring a PIN code requires API version 6.0",
+            ));
+        }
+
+        if version == ApiVersion::V5_1 && self.mrtd.is_some() {
+            return Err(Error::InvalidRequirement(
+                "Requiring an ID document check requires API version 6.0",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Data sent with a sign order but not shown to the user, e.g. a hash of a document. Sent base64
+/// encoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserNonVisibleData {
+    data: Vec<u8>,
+}
+
+impl UserNonVisibleData {
+    /// At most 200 000 characters once encoded.
+    pub const MAX_ENCODED_LEN: usize = 200_000;
+
+    pub fn new(data: impl Into<Vec<u8>>) -> Result<Self, Error> {
+        let data = data.into();
+
+        if data.is_empty() {
+            return Err(Error::InvalidUserData(
+                "User non-visible data must not be empty",
+            ));
+        }
+
+        if encoded_len(&data) > Self::MAX_ENCODED_LEN {
+            return Err(Error::InvalidUserData(
+                "User non-visible data exceeds 200 000 characters once base64 encoded",
+            ));
+        }
+
+        Ok(UserNonVisibleData { data })
+    }
+
+    /// Decodes data that is already base64 encoded, e.g. from a stored order.
+    pub fn from_base64(encoded: &str) -> Result<Self, Error> {
+        let data = STANDARD
+            .decode(encoded)
+            .map_err(|_| Error::InvalidUserData("User non-visible data isn't valid base64"))?;
+
+        Self::new(data)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn to_base64(&self) -> String {
+        STANDARD.encode(&self.data)
+    }
+}
+
+impl Serialize for UserNonVisibleData {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.to_base64().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for UserNonVisibleData {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        String::deserialize(deserializer).and_then(|encoded| {
+            UserNonVisibleData::from_base64(&encoded).map_err(serde::de::Error::custom)
+        })
+    }
+}
+
+/// How `user_visible_data` should be displayed. Plain text if not set.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum VisibleDataFormat {
+    /// The markdown subset described in BankID's documentation: headings, tables, emphasis and
+    /// horizontal rules.
+    SimpleMarkdownV1,
+}
+
+impl VisibleDataFormat {
+    /// Checks for common mistakes: headings deeper than three levels, ragged tables and unclosed
+    /// emphasis.
+    pub fn validate(&self, data: &UserVisibleData) -> Result<(), Error> {
+        match self {
+            Self::SimpleMarkdownV1 => validate_simple_markdown(data.text()),
+        }
+    }
+}
+
+// The characters of `line` that aren't escaped with a backslash.
+fn unescaped(line: &str) -> impl Iterator<Item = char> + '_ {
+    let mut escaped = false;
+
+    line.chars().filter(move |c| {
+        let keep = !escaped && *c != '\\';
+        escaped = !escaped && *c == '\\';
+        keep
+    })
+}
+
+fn validate_simple_markdown(text: &str) -> Result<(), Error> {
+    let mut table_columns = None;
+
+    for line in text.lines().map(str::trim_end) {
+        if let Some(heading) = line.strip_prefix('#') {
+            let title = heading.trim_start_matches('#');
+
+            if heading.len() - title.len() > 2 {
+                return Err(Error::InvalidUserData(
+                    "Headings can have at most three levels",
+                ));
+            }
+
+            if !title.starts_with(' ') {
+                return Err(Error::InvalidUserData(
+                    "Heading markers must be followed by a space",
+                ));
+            }
+        }
+
+        table_columns = if line.starts_with('|') {
+            if line.len() < 2 || !line.ends_with('|') {
+                return Err(Error::InvalidUserData("Table rows must end with |"));
+            }
+
+            let columns = unescaped(line).filter(|c| *c == '|').count() - 1;
+
+            match table_columns {
+                Some(expected) if expected != columns => {
+                    return Err(Error::InvalidUserData(
+                        "Every row of a table must have the same number of columns",
+                    ))
+                }
+                _ => Some(columns),
+            }
+        } else {
+            None
+        };
+
+        if unescaped(line).filter(|c| *c == '*').count() % 2 != 0 {
+            return Err(Error::InvalidUserData(
+                "Emphasis must be closed on the line it starts",
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// Checks the visible data against its format before an order is sent.
+pub(crate) fn validate_visible_data(
+    data: Option<&UserVisibleData>,
+    format: Option<VisibleDataFormat>,
+) -> Result<(), Error> {
+    match (data, format) {
+        (Some(data), Some(format)) => format.validate(data),
+        (None, Some(_)) => Err(Error::InvalidUserData(
+            "A visible data format requires user visible data",
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Builds a `Requirement`, leaving out everything that isn't set.
+#[derive(Debug, Clone, Default)]
+pub struct RequirementBuilder {
+    requirement: Requirement,
+}
+
+impl RequirementBuilder {
+    pub fn certificate_policies(
+        mut self,
+        policies: impl IntoIterator<Item = CertificatePolicy>,
+    ) -> RequirementBuilder {
+        self.requirement.certificate_policies = Some(policies.into_iter().collect());
+        self
+    }
+
+    pub fn allow_fingerprint(mut self, allow_fingerprint: bool) -> RequirementBuilder {
+        self.requirement.allow_fingerprint = Some(allow_fingerprint);
+        self
+    }
+
+    pub fn auto_start_token_required(mut self, required: bool) -> RequirementBuilder {
+        self.requirement.auto_start_token_required = Some(required);
+        self
+    }
+
+    pub fn issuer_cn(mut self, issuer_cn: bool) -> RequirementBuilder {
+        self.requirement.issuer_cn = Some(issuer_cn);
+        self
+    }
+
+    pub fn card_reader(mut self, card_reader: CardReaderClass) -> RequirementBuilder {
+        self.requirement.card_reader = Some(card_reader);
+        self
+    }
+
+    pub fn risk(mut self, risk: RiskThreshold) -> RequirementBuilder {
+        self.requirement.risk = Some(risk);
+        self
+    }
+
+    /// Require the user to confirm with their PIN code rather than biometrics, v6.0 only.
+    pub fn pin_code(mut self, pin_code: bool) -> RequirementBuilder {
+        self.requirement.pin_code = Some(pin_code);
+        self
+    }
+
+    /// Require the user to verify their identity with an ID document (MRTD), v6.0 only.
+    pub fn mrtd(mut self, mrtd: bool) -> RequirementBuilder {
+        self.requirement.mrtd = Some(mrtd);
+        self
+    }
+
+    /// Only let the user with `personal_number` complete the order. Sent as part of the order
+    /// rather than the requirement to v5.1.
+    pub fn personal_number(mut self, personal_number: PersonalNumber) -> RequirementBuilder {
+        self.requirement.personal_number = Some(personal_number);
+        self
+    }
+
+    pub fn build(self) -> Requirement {
+        self.requirement
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct AuthRequest {
-    pub end_user_ip: IpAddr,
+    pub end_user_ip: EndUserIp,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub personal_number: Option<PersonalNumber>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub requirement: Option<Requirement>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_visible_data: Option<UserVisibleData>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_visible_data_format: Option<VisibleDataFormat>,
+
+    /// Where the BankID app should send the user when the order is done, for same-device flows.
+    /// v6.0 only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub return_url: Option<String>,
+
+    /// Ask for BankID's risk assessment in the completion data, v6.0 only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub return_risk: Option<bool>,
+}
+
+/// Text shown to the user when signing, sent base64 encoded as BankID requires.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserVisibleData {
+    text: String,
+}
+
+impl UserVisibleData {
+    /// At most 40 000 characters once encoded.
+    pub const MAX_ENCODED_LEN: usize = 40_000;
+
+    pub fn new(text: impl Into<String>) -> Result<Self, Error> {
+        let text = text.into();
+
+        if text.is_empty() {
+            return Err(Error::InvalidUserData(
+                "User visible data must not be empty",
+            ));
+        }
+
+        if encoded_len(text.as_bytes()) > Self::MAX_ENCODED_LEN {
+            return Err(Error::InvalidUserData(
+                "User visible data exceeds 40 000 characters once base64 encoded",
+            ));
+        }
+
+        Ok(UserVisibleData { text })
+    }
+
+    /// Decodes data that is already base64 encoded, e.g. from a stored order.
+    pub fn from_base64(encoded: &str) -> Result<Self, Error> {
+        let bytes = STANDARD
+            .decode(encoded)
+            .map_err(|_| Error::InvalidUserData("User visible data isn't valid base64"))?;
+        let text = String::from_utf8(bytes)
+            .map_err(|_| Error::InvalidUserData("User visible data isn't valid UTF-8"))?;
+
+        Self::new(text)
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn to_base64(&self) -> String {
+        STANDARD.encode(&self.text)
+    }
+}
+
+fn encoded_len(data: &[u8]) -> usize {
+    data.len().div_ceil(3) * 4
+}
+
+impl Serialize for UserVisibleData {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.to_base64().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for UserVisibleData {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        String::deserialize(deserializer).and_then(|encoded| {
+            UserVisibleData::from_base64(&encoded).map_err(serde::de::Error::custom)
+        })
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct SignRequest {
-    pub end_user_ip: IpAddr,
+    pub end_user_ip: EndUserIp,
 
     pub personal_number: Option<PersonalNumber>,
 
@@ -53,20 +622,435 @@ pub struct SignRequest {
     pub requirement: Option<Requirement>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub user_visible_data: Option<String>,
+    pub user_visible_data: Option<UserVisibleData>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_visible_data_format: Option<VisibleDataFormat>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub user_non_visible_data: Option<String>,
+    pub user_non_visible_data: Option<UserNonVisibleData>,
+}
+
+// Serializes an auth or sign order into the JSON body expected by the given environment and API
+// version.
+pub(crate) fn order_body<T>(
+    request: &T,
+    requirement: Option<&Requirement>,
+    environment: Environment,
+    version: ApiVersion,
+) -> Result<serde_json::Value, Error>
+where
+    T: Serialize,
+{
+    if let Some(requirement) = requirement {
+        requirement.validate_for(version)?;
+    }
+
+    let mut body = serde_json::to_value(request)?;
+
+    if version == ApiVersion::V5_1 {
+        if body.get("returnUrl").is_some() {
+            return Err(Error::InvalidOrder("A return URL requires API version 6.0"));
+        }
+
+        if body.get("returnRisk").is_some() {
+            return Err(Error::InvalidOrder(
+                "Returning the risk requires API version 6.0",
+            ));
+        }
+    }
+
+    if let Some(policies) = requirement.and_then(|r| r.certificate_policies()) {
+        body["requirement"]["certificatePolicies"] = policies
+            .iter()
+            .map(|policy| policy.oid(environment))
+            .collect();
+    }
+
+    // From v6.0 the personal number is part of the requirement rather than the order, it may be
+    // given in either place and is moved to where the version expects it.
+    let in_order = take_field(&mut body, "personalNumber");
+    let in_requirement = body
+        .get_mut("requirement")
+        .and_then(|requirement| take_field(requirement, "personalNumber"));
+
+    let personal_number = match (in_order, in_requirement) {
+        (Some(in_order), Some(in_requirement)) if in_order != in_requirement => {
+            return Err(Error::InvalidRequirement(
+                "The personal numbers of the order and its requirement differ",
+            ))
+        }
+        (in_order, in_requirement) => in_order.or(in_requirement),
+    };
+
+    if let Some(personal_number) = personal_number {
+        match version {
+            ApiVersion::V5_1 => body["personalNumber"] = personal_number,
+            ApiVersion::V6_0 => body["requirement"]["personalNumber"] = personal_number,
+        }
+    }
+
+    if body["requirement"]
+        .as_object()
+        .is_some_and(|requirement| requirement.is_empty())
+    {
+        take_field(&mut body, "requirement");
+    }
+
+    Ok(body)
+}
+
+// Removes `field` from a JSON object, returning it unless it's null.
+fn take_field(value: &mut serde_json::Value, field: &str) -> Option<serde_json::Value> {
+    value
+        .as_object_mut()
+        .and_then(|object| object.remove(field))
+        .filter(|value| !value.is_null())
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct CollectRequest {
-    pub order_ref: Uuid,
+    pub order_ref: OrderRef,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct CancelRequest {
-    pub order_ref: Uuid,
+    pub order_ref: OrderRef,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    use super::{
+        certificate_policies, order_body, AuthRequest, CardReaderClass, CertificatePolicy,
+        EndUserIp, Requirement, RiskThreshold, SignRequest, UserNonVisibleData, UserVisibleData,
+        VisibleDataFormat,
+    };
+    use crate::{ApiVersion, Environment, PersonalNumber};
+
+    #[test]
+    fn test_end_user_ip_is_public() {
+        fn case(addr: IpAddr, expected: bool) {
+            assert_eq!(
+                EndUserIp::from_socket(addr).is_public(),
+                expected,
+                "{}",
+                addr
+            );
+        }
+
+        case(IpAddr::V4(Ipv4Addr::new(81, 170, 1, 1)), true);
+        case(IpAddr::V4(Ipv4Addr::LOCALHOST), false);
+        case(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), false);
+        case(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1)), false);
+        case(IpAddr::V4(Ipv4Addr::new(169, 254, 0, 1)), false);
+        case(
+            IpAddr::V6(Ipv6Addr::new(0x2a00, 0x1450, 0, 0, 0, 0, 0, 1)),
+            true,
+        );
+        case(IpAddr::V6(Ipv6Addr::LOCALHOST), false);
+        case(
+            IpAddr::V6(Ipv6Addr::new(0xfd00, 0, 0, 0, 0, 0, 0, 1)),
+            false,
+        );
+        case(
+            IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1)),
+            false,
+        );
+        case(
+            IpAddr::V6(Ipv4Addr::new(192, 168, 1, 1).to_ipv6_mapped()),
+            false,
+        );
+
+        case(IpAddr::V4(Ipv4Addr::new(100, 64, 0, 1)), false);
+        case(IpAddr::V4(Ipv4Addr::new(100, 127, 255, 254)), false);
+        case(IpAddr::V4(Ipv4Addr::new(100, 128, 0, 1)), true);
+        case(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)), false);
+        case(IpAddr::V4(Ipv4Addr::new(198, 51, 100, 1)), false);
+        case(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 1)), false);
+        case(IpAddr::V4(Ipv4Addr::new(203, 0, 114, 1)), true);
+        case(IpAddr::V4(Ipv4Addr::new(198, 18, 0, 1)), false);
+        case(IpAddr::V4(Ipv4Addr::new(198, 19, 255, 1)), false);
+        case(IpAddr::V4(Ipv4Addr::new(198, 20, 0, 1)), true);
+        case(IpAddr::V4(Ipv4Addr::new(240, 0, 0, 1)), false);
+        case(IpAddr::V4(Ipv4Addr::new(223, 255, 255, 1)), true);
+        case(
+            IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)),
+            false,
+        );
+        case(
+            IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb9, 0, 0, 0, 0, 0, 1)),
+            true,
+        );
+        case(
+            IpAddr::V6(Ipv4Addr::new(100, 64, 0, 1).to_ipv6_mapped()),
+            false,
+        );
+        case(IpAddr::V4(Ipv4Addr::new(0, 1, 2, 3)), false);
+        case(IpAddr::V4(Ipv4Addr::new(192, 0, 0, 9)), false);
+        case(IpAddr::V4(Ipv4Addr::new(192, 0, 1, 1)), true);
+        case(IpAddr::V4(Ipv4Addr::new(224, 0, 0, 251)), false);
+        case(IpAddr::V4(Ipv4Addr::new(239, 255, 255, 250)), false);
+        case(
+            IpAddr::V6(Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 1)),
+            false,
+        );
+        case(
+            IpAddr::V6(Ipv4Addr::new(224, 0, 0, 251).to_ipv6_mapped()),
+            false,
+        );
+        case(
+            IpAddr::V6(Ipv4Addr::new(81, 170, 1, 1).to_ipv6_mapped()),
+            true,
+        );
+    }
+
+    #[test]
+    fn test_end_user_ip_serde() {
+        let ip = EndUserIp::from_forwarded_for(IpAddr::V4(Ipv4Addr::new(81, 170, 1, 1)), 1);
+
+        assert_eq!(
+            serde_json::to_string(&ip).expect("Failed to serialize ip"),
+            r#""81.170.1.1""#
+        );
+    }
+
+    #[test]
+    fn test_certificate_policy_oid() {
+        let policy = CertificatePolicy::MobileBankId;
+
+        assert_eq!(policy.oid(Environment::Production), "1.2.752.78.1.5");
+        assert_eq!(policy.oid(Environment::Test), "1.2.3.4.25");
+        assert_eq!(CertificatePolicy::from_oid("1.2.3.4.25"), policy);
+        assert_eq!(
+            CertificatePolicy::from_oid("1.2.3.4.99"),
+            CertificatePolicy::Oid("1.2.3.4.99".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_order_body_personal_number() {
+        let request = AuthRequest {
+            end_user_ip: IpAddr::V4(Ipv4Addr::LOCALHOST).into(),
+            personal_number: Some(PersonalNumber::parse("199001019802").expect("Parsing failed")),
+            requirement: None,
+            user_visible_data: None,
+            user_visible_data_format: None,
+            return_url: None,
+            return_risk: None,
+        };
+
+        let body = order_body(&request, None, Environment::Test, ApiVersion::V5_1)
+            .expect("Failed to serialize order");
+        assert_eq!(body["personalNumber"], "199001019802");
+
+        let body = order_body(&request, None, Environment::Test, ApiVersion::V6_0)
+            .expect("Failed to serialize order");
+        assert!(body.get("personalNumber").is_none());
+        assert_eq!(body["requirement"]["personalNumber"], "199001019802");
+
+        let request = SignRequest {
+            end_user_ip: IpAddr::V4(Ipv4Addr::LOCALHOST).into(),
+            personal_number: None,
+            requirement: None,
+            user_visible_data: None,
+            user_visible_data_format: None,
+            user_non_visible_data: None,
+        };
+
+        let body = order_body(&request, None, Environment::Test, ApiVersion::V6_0)
+            .expect("Failed to serialize order");
+        assert!(body.get("personalNumber").is_none());
+        assert!(body.get("requirement").is_none());
+    }
+
+    #[test]
+    fn test_requirement_personal_number() {
+        let personal_number = PersonalNumber::parse("199001019802").expect("Parsing failed");
+        let request = |order_pno, requirement_pno: Option<PersonalNumber>| AuthRequest {
+            end_user_ip: IpAddr::V4(Ipv4Addr::LOCALHOST).into(),
+            personal_number: order_pno,
+            requirement: requirement_pno.map(|personal_number| {
+                Requirement::builder()
+                    .personal_number(personal_number)
+                    .build()
+            }),
+            user_visible_data: None,
+            user_visible_data_format: None,
+            return_url: None,
+            return_risk: None,
+        };
+        let body = |request: &AuthRequest, version| {
+            order_body(
+                request,
+                request.requirement.as_ref(),
+                Environment::Test,
+                version,
+            )
+        };
+
+        let in_requirement = request(None, Some(personal_number));
+        let v5 = body(&in_requirement, ApiVersion::V5_1).expect("Failed to serialize order");
+        assert_eq!(v5["personalNumber"], "199001019802");
+        assert!(v5.get("requirement").is_none());
+        let v6 = body(&in_requirement, ApiVersion::V6_0).expect("Failed to serialize order");
+        assert_eq!(v6["requirement"]["personalNumber"], "199001019802");
+
+        let other = PersonalNumber::parse("198506159816").expect("Parsing failed");
+        assert!(body(
+            &request(Some(personal_number), Some(personal_number)),
+            ApiVersion::V6_0
+        )
+        .is_ok());
+        assert!(body(
+            &request(Some(other), Some(personal_number)),
+            ApiVersion::V6_0
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_return_url() {
+        let request = AuthRequest {
+            end_user_ip: IpAddr::V4(Ipv4Addr::LOCALHOST).into(),
+            personal_number: None,
+            requirement: None,
+            user_visible_data: None,
+            user_visible_data_format: None,
+            return_url: Some("https://example.com/login#nonce=1".to_owned()),
+            return_risk: Some(true),
+        };
+
+        let body = order_body(&request, None, Environment::Test, ApiVersion::V6_0)
+            .expect("Failed to serialize order");
+        assert_eq!(body["returnUrl"], "https://example.com/login#nonce=1");
+        assert_eq!(body["returnRisk"], true);
+
+        assert!(order_body(&request, None, Environment::Test, ApiVersion::V5_1).is_err());
+    }
+
+    #[test]
+    fn test_requirement_v6_fields() {
+        let requirement = Requirement::builder().pin_code(true).mrtd(true).build();
+
+        assert!(requirement.validate_for(ApiVersion::V5_1).is_err());
+        assert!(requirement.validate_for(ApiVersion::V6_0).is_ok());
+        assert_eq!(
+            serde_json::to_value(&requirement).expect("Failed to serialize requirement"),
+            serde_json::json!({ "pinCode": true, "mrtd": true })
+        );
+    }
+
+    #[test]
+    fn test_requirement_builder() {
+        let requirement = Requirement::builder()
+            .certificate_policies([CertificatePolicy::MobileBankId])
+            .allow_fingerprint(true)
+            .card_reader(CardReaderClass::Class2)
+            .build();
+
+        assert_eq!(
+            serde_json::to_value(&requirement).expect("Failed to serialize requirement"),
+            serde_json::json!({
+                "certificatePolicies": [certificate_policies::MOBILE_BANKID],
+                "allowFingerprint": true,
+                "cardReader": "class2",
+            })
+        );
+        assert_eq!(
+            serde_json::to_string(&Requirement::builder().build())
+                .expect("Failed to serialize requirement"),
+            "{}"
+        );
+    }
+
+    #[test]
+    fn test_requirement_risk() {
+        let requirement = Requirement {
+            certificate_policies: None,
+            allow_fingerprint: None,
+            auto_start_token_required: None,
+            issuer_cn: None,
+            card_reader: None,
+            risk: Some(RiskThreshold::Moderate),
+            pin_code: None,
+            mrtd: None,
+            personal_number: None,
+        };
+
+        assert!(requirement.validate_for(ApiVersion::V5_1).is_err());
+        assert!(requirement.validate_for(ApiVersion::V6_0).is_ok());
+        assert_eq!(
+            serde_json::to_value(&requirement).expect("Failed to serialize requirement")["risk"],
+            "moderate"
+        );
+    }
+
+    #[test]
+    fn test_user_visible_data() {
+        let data = UserVisibleData::new("Överföring 100 kr").expect("Invalid user visible data");
+
+        assert_eq!(
+            serde_json::to_value(&data).expect("Failed to serialize data"),
+            "w5Z2ZXJmw7ZyaW5nIDEwMCBrcg=="
+        );
+        assert_eq!(
+            serde_json::from_str::<UserVisibleData>(r#""w5Z2ZXJmw7ZyaW5nIDEwMCBrcg==""#)
+                .expect("Failed to deserialize data"),
+            data
+        );
+
+        assert!(UserVisibleData::new("").is_err());
+        assert!(UserVisibleData::new("a".repeat(30_000)).is_ok());
+        assert!(UserVisibleData::new("a".repeat(30_001)).is_err());
+        assert!(UserVisibleData::from_base64("not base64").is_err());
+    }
+
+    #[test]
+    fn test_simple_markdown() {
+        let validate = |text: &str| {
+            VisibleDataFormat::SimpleMarkdownV1
+                .validate(&UserVisibleData::new(text).expect("Invalid user visible data"))
+        };
+
+        assert!(validate(
+            "# Avtal\n\n| Belopp | Mottagare |\n|---|---|\n| 100 kr | Anna |\n\n**Signera** *nu*\n---"
+        )
+        .is_ok());
+        assert!(validate("Pris 5 \\* 3").is_ok());
+
+        assert!(validate("#### Too deep").is_err());
+        assert!(validate("#Missing space").is_err());
+        assert!(validate("| a | b |\n| c |").is_err());
+        assert!(validate("| a | b").is_err());
+        assert!(validate("**Unclosed*").is_err());
+
+        assert_eq!(
+            serde_json::to_value(VisibleDataFormat::SimpleMarkdownV1)
+                .expect("Failed to serialize format"),
+            "simpleMarkdownV1"
+        );
+    }
+
+    #[test]
+    fn test_user_non_visible_data() {
+        let data = UserNonVisibleData::new([0xde, 0xad, 0xbe, 0xef])
+            .expect("Invalid user non-visible data");
+
+        assert_eq!(
+            serde_json::to_value(&data).expect("Failed to serialize data"),
+            "3q2+7w=="
+        );
+        assert_eq!(
+            UserNonVisibleData::from_base64("3q2+7w==").expect("Invalid base64"),
+            data
+        );
+
+        assert!(UserNonVisibleData::new(Vec::new()).is_err());
+        assert!(UserNonVisibleData::new(vec![0; 150_000]).is_ok());
+        assert!(UserNonVisibleData::new(vec![0; 150_001]).is_err());
+    }
 }
@@ -1,19 +1,53 @@
-use crate::PersonalNumber;
+use crate::signature::{self, CertificateInfo, SignedData};
+use crate::{
+    AutoStartToken, Error, HttpStatus, OrderRef, PersonalNumber, QrStartSecret, QrStartToken,
+};
+use reqwest::header::HeaderMap;
 use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::{fmt::Display, net::IpAddr};
-use uuid::Uuid;
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+/// A successful response together with its status and the response headers that
+/// were asked for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Envelope<T> {
+    pub status: HttpStatus,
+    pub headers: HeaderMap,
+    pub body: T,
+}
+
+impl<T> Envelope<T> {
+    pub fn map<U, F>(self, f: F) -> Envelope<U>
+    where
+        F: FnOnce(T) -> U,
+    {
+        Envelope {
+            status: self.status,
+            headers: self.headers,
+            body: f(self.body),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct OrderResponse {
-    pub order_ref: Uuid,
-    pub auto_start_token: Uuid,
-    pub qr_start_token: Uuid,
-    pub qr_start_secret: Uuid,
+    pub order_ref: OrderRef,
+    pub auto_start_token: AutoStartToken,
+    pub qr_start_token: QrStartToken,
+    pub qr_start_secret: QrStartSecret,
+}
+
+impl OrderResponse {
+    /// A short line for logs, without tokens or secrets.
+    pub fn summary(&self) -> String {
+        format!("order {}", self.order_ref.short())
+    }
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
 pub enum ErrorCode {
     AlreadyInProgress,
     InvalidParameters,
@@ -24,16 +58,23 @@ pub enum ErrorCode {
     UnsupportedMediaType,
     InternalError,
     Maintenance,
+    /// A code this version of the crate doesn't know about, as sent by BankID.
+    #[serde(untagged)]
+    Unknown(String),
 }
 
 impl Display for ErrorCode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+        match self {
+            Self::Unknown(code) => write!(f, "{}", code),
+            _ => write!(f, "{:?}", self),
+        }
     }
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
 pub struct ClientError {
     pub error_code: ErrorCode,
     pub details: String,
@@ -45,8 +86,11 @@ impl Display for ClientError {
     }
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+impl std::error::Error for ClientError {}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
 pub enum CollectHintCode {
     OutstandingTransaction,
     NoClient,
@@ -58,17 +102,36 @@ pub enum CollectHintCode {
     #[serde(rename = "cancelled")]
     Canceled,
     StartFailed,
+    UserMrtd,
+    UserCallConfirm,
+    NotSupportedByUserApp,
+    /// A code this version of the crate doesn't know about, as sent by BankID.
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+impl Display for CollectHintCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unknown(code) => write!(f, "{}", code),
+            _ => write!(f, "{:?}", self),
+        }
+    }
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
 pub enum CollectStatus {
     Pending,
     Failed,
     Complete,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+/// `Debug` masks the personal number and leaves out the names, unless the `unredacted-debug`
+/// feature is enabled.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "unredacted-debug", derive(Debug))]
 #[serde(rename_all = "camelCase")]
 pub struct User {
     pub personal_number: PersonalNumber,
@@ -77,41 +140,569 @@ pub struct User {
     pub surname: String,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg(not(feature = "unredacted-debug"))]
+impl std::fmt::Debug for User {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("User")
+            .field("personal_number", &self.personal_number)
+            .finish_non_exhaustive()
+    }
+}
+
+// Lowercase surname prefixes, as in "von Essen" or "af Klint", which don't give initials.
+fn is_name_particle(part: &str) -> bool {
+    part.chars().next().is_none_or(char::is_lowercase)
+}
+
+impl User {
+    /// Given names followed by surname, e.g. "Anna Maria von Essen".
+    pub fn display_name(&self) -> String {
+        join_names(&self.given_name, &self.surname, " ").unwrap_or_else(|| self.name.to_owned())
+    }
+
+    /// Surname followed by given names, e.g. "von Essen, Anna Maria", for sorted lists.
+    pub fn surname_first(&self) -> String {
+        join_names(&self.surname, &self.given_name, ", ").unwrap_or_else(|| self.name.to_owned())
+    }
+
+    /// The first letter of every given name and surname part, e.g. "AMLH" for "Anna-Maria
+    /// Lind-Holm". Particles like "von" are left out.
+    pub fn initials(&self) -> String {
+        self.given_name
+            .split_whitespace()
+            .chain(self.surname.split_whitespace())
+            .filter(|part| !is_name_particle(part))
+            .flat_map(|part| part.split('-'))
+            .filter_map(|part| part.chars().next())
+            .flat_map(char::to_uppercase)
+            .collect()
+    }
+}
+
+fn join_names(first: &str, second: &str, separator: &str) -> Option<String> {
+    match (first.trim(), second.trim()) {
+        ("", "") => None,
+        (name, "") | ("", name) => Some(name.to_owned()),
+        (first, second) => Some(format!("{}{}{}", first, separator, second)),
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct Device {
     pub ip_address: IpAddr,
+    /// Identifies the device the order was completed on, only returned from v6.0.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uhi: Option<String>,
+}
+
+/// Whether an order was completed on the same device as an earlier one, see
+/// `Device::continuity`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceContinuity {
+    Match,
+    /// Another device than before, e.g. a reason to ask the user for a step-up.
+    Changed,
+    /// There's nothing to compare, the uhi is missing from either order.
+    Unknown,
+}
+
+impl Device {
+    /// Compares the uhi with `stored`, the one saved for the user after an earlier order.
+    pub fn continuity(&self, stored: Option<&str>) -> DeviceContinuity {
+        match (self.uhi.as_deref(), stored) {
+            (Some(uhi), Some(stored)) if uhi == stored => DeviceContinuity::Match,
+            (Some(_), Some(_)) => DeviceContinuity::Changed,
+            _ => DeviceContinuity::Unknown,
+        }
+    }
+}
+
+/// Checks the user went through beyond the requirement, only returned from v6.0.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct StepUp {
+    /// Whether the user's identity was verified with an ID document.
+    pub mrtd: bool,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct Cert {
+    #[serde(deserialize_with = "string_or_number")]
     pub not_before: String,
+    #[serde(deserialize_with = "string_or_number")]
     pub not_after: String,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+// The number of milliseconds since the Unix epoch in a `Cert` field.
+fn epoch_millis(value: &str) -> Option<u64> {
+    value.trim().parse().ok()
+}
+
+impl Cert {
+    /// Start of the certificate's validity, `None` if BankID sent something other than epoch
+    /// milliseconds.
+    pub fn valid_from(&self) -> Option<SystemTime> {
+        epoch_millis(&self.not_before)
+            .and_then(|millis| UNIX_EPOCH.checked_add(Duration::from_millis(millis)))
+    }
+
+    /// End of the certificate's validity, see `valid_from`.
+    pub fn valid_until(&self) -> Option<SystemTime> {
+        epoch_millis(&self.not_after)
+            .and_then(|millis| UNIX_EPOCH.checked_add(Duration::from_millis(millis)))
+    }
+
+    /// Whether `time` is within the validity period. `false` if either end can't be parsed.
+    pub fn is_valid_at(&self, time: SystemTime) -> bool {
+        match (self.valid_from(), self.valid_until()) {
+            (Some(from), Some(until)) => from <= time && time <= until,
+            _ => false,
+        }
+    }
+
+    #[cfg(feature = "chrono")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "chrono")))]
+    pub fn not_before_utc(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        epoch_millis(&self.not_before)
+            .and_then(|millis| i64::try_from(millis).ok())
+            .and_then(chrono::DateTime::from_timestamp_millis)
+    }
+
+    #[cfg(feature = "chrono")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "chrono")))]
+    pub fn not_after_utc(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        epoch_millis(&self.not_after)
+            .and_then(|millis| i64::try_from(millis).ok())
+            .and_then(chrono::DateTime::from_timestamp_millis)
+    }
+}
+
+// BankID has been known to send numeric fields both as strings and as numbers, accept either.
+fn string_or_number<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrNumber {
+        String(String),
+        Number(serde_json::Number),
+    }
+
+    Ok(match StringOrNumber::deserialize(deserializer)? {
+        StringOrNumber::String(value) => value,
+        StringOrNumber::Number(value) => value.to_string(),
+    })
+}
+
+/// BankID's assessment of the risk that the order is fraudulent.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Risk {
+    Low,
+    Moderate,
+    High,
+    /// A level this version of the crate doesn't know about, as sent by BankID.
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+/// `Debug` leaves out the signature, which contains the personal number, unless the
+/// `unredacted-debug` feature is enabled.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "unredacted-debug", derive(Debug))]
 #[serde(rename_all = "camelCase")]
 pub struct CompletionData {
     pub user: User,
     pub device: Device,
-    pub cert: Cert,
+    /// Not returned from v6.0.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cert: Option<Cert>,
     pub signature: String,
     pub ocsp_response: String,
+    /// Only returned from v6.0, for orders with `return_risk` set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub risk: Option<Risk>,
+    /// When the user's BankID was issued, as `YYYY-MM-DD`. Only returned from v6.0.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bank_id_issue_date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step_up: Option<StepUp>,
+}
+
+#[cfg(not(feature = "unredacted-debug"))]
+impl std::fmt::Debug for CompletionData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompletionData")
+            .field("user", &self.user)
+            .field("device", &self.device)
+            .field("cert", &self.cert)
+            .field("ocsp_response", &self.ocsp_response)
+            .field("risk", &self.risk)
+            .field("bank_id_issue_date", &self.bank_id_issue_date)
+            .field("step_up", &self.step_up)
+            .finish_non_exhaustive()
+    }
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+impl CompletionData {
+    /// A short line for logs, with the personal number masked.
+    pub fn summary(&self) -> String {
+        format!("user {}", self.user.personal_number.masked())
+    }
+
+    pub fn user_certificate(&self) -> Result<CertificateInfo, Error> {
+        signature::user_certificate(&self.signature)
+    }
+
+    /// What the user signed, parsed out of `signature`.
+    pub fn signed_data(&self) -> Result<SignedData, Error> {
+        signature::signed_data(&self.signature)
+    }
+}
+
+// Completion data is large, but boxing it would be a breaking change for little gain.
+#[allow(clippy::large_enum_variant)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 #[serde(tag = "status")]
 #[serde(rename_all = "camelCase")]
 pub enum CollectResponse {
     #[serde(rename_all = "camelCase")]
-    Pending { hint_code: CollectHintCode, order_ref: Uuid },
+    Pending {
+        hint_code: CollectHintCode,
+        order_ref: OrderRef,
+    },
     #[serde(rename_all = "camelCase")]
-    Failed { hint_code: CollectHintCode, order_ref: Uuid  },
+    Failed {
+        hint_code: CollectHintCode,
+        order_ref: OrderRef,
+    },
     #[serde(rename_all = "camelCase")]
-    Complete { completion_data: CompletionData, order_ref: Uuid },
+    Complete {
+        completion_data: CompletionData,
+        order_ref: OrderRef,
+    },
+}
+
+/// The shape of a collect response expected by BankID's frontend examples: only `status` and
+/// `hintCode`, leaving out the order ref and any completion data.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
+pub struct FrontendCollectResponse {
+    pub status: CollectStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts-rs", ts(optional))]
+    pub hint_code: Option<CollectHintCode>,
+}
+
+/// Writes TypeScript definitions of the types sent to frontends, `FrontendCollectResponse` and
+/// `ClientError` and the types they use, to one file per type in `dir`.
+#[cfg(feature = "ts-rs")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ts-rs")))]
+pub fn export_typescript(dir: impl AsRef<std::path::Path>) -> Result<(), ts_rs::ExportError> {
+    use ts_rs::TS;
+
+    FrontendCollectResponse::export_all_to(dir.as_ref())?;
+    ClientError::export_all_to(dir.as_ref())
+}
+
+impl From<&CollectResponse> for FrontendCollectResponse {
+    fn from(response: &CollectResponse) -> Self {
+        match response {
+            CollectResponse::Pending { hint_code, .. } => FrontendCollectResponse {
+                status: CollectStatus::Pending,
+                hint_code: Some(hint_code.to_owned()),
+            },
+            CollectResponse::Failed { hint_code, .. } => FrontendCollectResponse {
+                status: CollectStatus::Failed,
+                hint_code: Some(hint_code.to_owned()),
+            },
+            CollectResponse::Complete { .. } => FrontendCollectResponse {
+                status: CollectStatus::Complete,
+                hint_code: None,
+            },
+        }
+    }
+}
+
+impl CollectResponse {
+    pub fn to_frontend(&self) -> FrontendCollectResponse {
+        FrontendCollectResponse::from(self)
+    }
+
+    /// A short line for logs, with the personal number masked.
+    pub fn summary(&self) -> String {
+        match self {
+            Self::Pending {
+                hint_code,
+                order_ref,
+            } => format!("order {} pending ({})", order_ref.short(), hint_code),
+            Self::Failed {
+                hint_code,
+                order_ref,
+            } => format!("order {} failed ({})", order_ref.short(), hint_code),
+            Self::Complete {
+                completion_data,
+                order_ref,
+            } => format!(
+                "order {} complete, {}",
+                order_ref.short(),
+                completion_data.summary()
+            ),
+        }
+    }
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct CancelResponse {}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Cert, ClientError, CollectHintCode, CollectResponse, CompletionData, DeviceContinuity,
+        ErrorCode, Risk, StepUp, User,
+    };
+    use crate::testenv;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_cert_string_or_number() {
+        let cert: Cert =
+            serde_json::from_str(r#"{"notBefore":"1502983274000","notAfter":1563549999000}"#)
+                .expect("Failed to deserialize cert");
+
+        assert_eq!(cert.not_before, "1502983274000");
+        assert_eq!(cert.not_after, "1563549999000");
+
+        let valid_from = UNIX_EPOCH + Duration::from_millis(1502983274000);
+        assert_eq!(cert.valid_from(), Some(valid_from));
+        assert!(cert.is_valid_at(valid_from + Duration::from_secs(60)));
+        assert!(!cert.is_valid_at(UNIX_EPOCH));
+
+        let invalid = Cert {
+            not_before: "yesterday".to_owned(),
+            ..cert.to_owned()
+        };
+        assert_eq!(invalid.valid_from(), None);
+        assert!(!invalid.is_valid_at(valid_from));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_cert_chrono() {
+        let cert = Cert {
+            not_before: "1502983274000".to_owned(),
+            not_after: "1563549999000".to_owned(),
+        };
+
+        assert_eq!(
+            cert.not_before_utc(),
+            chrono::NaiveDate::from_ymd_opt(2017, 8, 17)
+                .and_then(|date| date.and_hms_opt(15, 21, 14))
+                .map(|time| time.and_utc())
+        );
+        assert!(cert.not_after_utc().is_some());
+    }
+
+    #[test]
+    fn test_unknown_error_code() {
+        let error: ClientError =
+            serde_json::from_str(r#"{"errorCode":"tooManyRequests","details":"Slow down"}"#)
+                .expect("Failed to deserialize error");
+
+        assert_eq!(
+            error.error_code,
+            ErrorCode::Unknown("tooManyRequests".to_owned())
+        );
+        assert_eq!(error.to_string(), "Error: tooManyRequests: Slow down");
+        assert_eq!(
+            serde_json::to_string(&error.error_code).expect("Failed to serialize error code"),
+            r#""tooManyRequests""#
+        );
+
+        let error: ClientError =
+            serde_json::from_str(r#"{"errorCode":"maintenance","details":""}"#)
+                .expect("Failed to deserialize error");
+        assert_eq!(error.error_code, ErrorCode::Maintenance);
+    }
+
+    #[test]
+    fn test_hint_code_names() {
+        for (hint_code, name) in [
+            (CollectHintCode::UserMrtd, "userMrtd"),
+            (CollectHintCode::UserCallConfirm, "userCallConfirm"),
+            (
+                CollectHintCode::NotSupportedByUserApp,
+                "notSupportedByUserApp",
+            ),
+        ] {
+            let json = format!(r#""{}""#, name);
+
+            assert_eq!(
+                serde_json::to_string(&hint_code).expect("Failed to serialize hint code"),
+                json
+            );
+            assert_eq!(
+                serde_json::from_str::<CollectHintCode>(&json)
+                    .expect("Failed to deserialize hint code"),
+                hint_code
+            );
+        }
+    }
+
+    #[test]
+    fn test_unknown_hint_code() {
+        let response: CollectResponse = serde_json::from_str(
+            r#"{"orderRef":"131daac9-16c6-4618-beb0-365768f37288","status":"pending","hintCode":"userVerifying"}"#,
+        )
+        .expect("Failed to deserialize collect response");
+
+        let hint_code = CollectHintCode::Unknown("userVerifying".to_owned());
+        assert_eq!(response.to_frontend().hint_code, Some(hint_code.clone()));
+        assert_eq!(hint_code.to_string(), "userVerifying");
+        assert_eq!(
+            serde_json::to_string(&CollectHintCode::Canceled)
+                .expect("Failed to serialize hint code"),
+            r#""cancelled""#
+        );
+    }
+
+    #[cfg(not(feature = "unredacted-debug"))]
+    #[test]
+    fn test_redacted_debug() {
+        let response: CollectResponse = serde_json::from_str(crate::golden::COLLECT_COMPLETE)
+            .expect("Failed to deserialize collect response");
+        let debug = format!("{:?}", response);
+
+        assert!(debug.contains("19900101****"));
+        assert!(!debug.contains("9802"));
+        assert!(!debug.contains("Testsson"));
+        assert!(!debug.contains("PD94bWwg"));
+    }
+
+    #[test]
+    fn test_completion_risk() {
+        let mut completion_data: serde_json::Value =
+            serde_json::from_str(crate::golden::COLLECT_COMPLETE)
+                .expect("Failed to deserialize fixture");
+        let completion_data = &mut completion_data["completionData"];
+
+        let without_risk: CompletionData = serde_json::from_value(completion_data.to_owned())
+            .expect("Failed to deserialize completion data");
+        assert_eq!(without_risk.risk, None);
+
+        for (raw, risk) in [
+            ("moderate", Risk::Moderate),
+            ("extreme", Risk::Unknown("extreme".to_owned())),
+        ] {
+            completion_data["risk"] = raw.into();
+            let with_risk: CompletionData = serde_json::from_value(completion_data.to_owned())
+                .expect("Failed to deserialize completion data");
+            assert_eq!(with_risk.risk, Some(risk));
+        }
+    }
+
+    #[test]
+    fn test_completion_v6_fields() {
+        let mut completion_data: serde_json::Value =
+            serde_json::from_str(crate::golden::COLLECT_COMPLETE)
+                .expect("Failed to deserialize fixture");
+        let completion_data = &mut completion_data["completionData"];
+        completion_data["bankIdIssueDate"] = "2020-02-01".into();
+        completion_data["stepUp"] = serde_json::json!({ "mrtd": true });
+        completion_data["device"]["uhi"] = "OZvYM9VvyiAmG7NA5jU5zRGcTmi5qFdl".into();
+
+        let parsed: CompletionData = serde_json::from_value(completion_data.to_owned())
+            .expect("Failed to deserialize completion data");
+        assert_eq!(parsed.bank_id_issue_date.as_deref(), Some("2020-02-01"));
+        assert_eq!(parsed.step_up, Some(StepUp { mrtd: true }));
+        assert_eq!(
+            parsed.device.uhi.as_deref(),
+            Some("OZvYM9VvyiAmG7NA5jU5zRGcTmi5qFdl")
+        );
+        assert_eq!(
+            parsed
+                .device
+                .continuity(Some("OZvYM9VvyiAmG7NA5jU5zRGcTmi5qFdl")),
+            DeviceContinuity::Match
+        );
+        assert_eq!(
+            parsed.device.continuity(Some("bm90IHRoZSBzYW1lIGRldmljZQ")),
+            DeviceContinuity::Changed
+        );
+        assert_eq!(parsed.device.continuity(None), DeviceContinuity::Unknown);
+        assert_eq!(
+            &serde_json::to_value(&parsed).expect("Failed to serialize completion data"),
+            completion_data
+        );
+    }
+
+    #[test]
+    fn test_collect_response_summary() {
+        let response: CollectResponse = serde_json::from_str(
+            r#"{"orderRef":"131daac9-16c6-4618-beb0-365768f37288","status":"pending","hintCode":"userSign"}"#,
+        )
+        .expect("Failed to deserialize collect response");
+
+        assert_eq!(response.summary(), "order 131daac9 pending (UserSign)");
+    }
+
+    #[test]
+    fn test_collect_response_frontend() {
+        let response: CollectResponse = serde_json::from_str(
+            r#"{"orderRef":"131daac9-16c6-4618-beb0-365768f37288","status":"failed","hintCode":"cancelled"}"#,
+        )
+        .expect("Failed to deserialize collect response");
+
+        assert_eq!(
+            serde_json::to_string(&response.to_frontend()).expect("Failed to serialize response"),
+            r#"{"status":"failed","hintCode":"cancelled"}"#
+        );
+    }
+
+    #[test]
+    fn test_user_names() {
+        let user = |given_name: &str, surname: &str| User {
+            personal_number: testenv::DEMO_PERSONAL_NUMBERS[0],
+            name: format!("{} {}", given_name, surname),
+            given_name: given_name.to_owned(),
+            surname: surname.to_owned(),
+        };
+
+        let user_a = user("Anna Maria", "von Essen");
+        assert_eq!(user_a.display_name(), "Anna Maria von Essen");
+        assert_eq!(user_a.surname_first(), "von Essen, Anna Maria");
+        assert_eq!(user_a.initials(), "AME");
+
+        let user_b = user("Karl-Johan", "Svensson Lind-Holm");
+        assert_eq!(user_b.surname_first(), "Svensson Lind-Holm, Karl-Johan");
+        assert_eq!(user_b.initials(), "KJSLH");
+
+        let user_c = user("Östen", "");
+        assert_eq!(user_c.display_name(), "Östen");
+        assert_eq!(user_c.surname_first(), "Östen");
+        assert_eq!(user_c.initials(), "Ö");
+    }
+
+    #[cfg(feature = "ts-rs")]
+    #[test]
+    fn test_typescript() {
+        use ts_rs::TS;
+
+        assert_eq!(
+            super::FrontendCollectResponse::decl(),
+            "type FrontendCollectResponse = { status: CollectStatus, hintCode?: CollectHintCode, };"
+        );
+        assert!(CollectHintCode::decl().ends_with(r#"| "notSupportedByUserApp" | string;"#));
+
+        let dir = std::env::temp_dir().join(format!("bankid-ts-{}", std::process::id()));
+        super::export_typescript(&dir).expect("Failed to export types");
+        assert!(dir.join("ErrorCode.ts").exists());
+        assert!(dir.join("CollectHintCode.ts").exists());
+        std::fs::remove_dir_all(&dir).expect("Failed to remove export directory");
+    }
+}
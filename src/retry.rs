@@ -0,0 +1,251 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use crate::response::{ClientError, ErrorCode};
+use crate::{ApiPath, Error};
+
+/// When to retry requests that failed because of transient BankID or network problems.
+///
+/// `internalError` and `maintenance` responses, connection errors and timeouts are retried with
+/// exponential backoff and full jitter, until the attempts are used up or the next attempt would
+/// start after the max elapsed time.
+///
+/// Only collect and cancel requests are retried unless `retry_order_creation` is set.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    max_elapsed: Option<Duration>,
+    retry_order_creation: bool,
+}
+
+impl RetryPolicy {
+    /// Makes at most `max_attempts` attempts, including the first.
+    pub fn new(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: max_attempts.max(1),
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+            max_elapsed: None,
+            retry_order_creation: false,
+        }
+    }
+
+    /// Backoff before the first retry, doubling for every retry up to `max`.
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> RetryPolicy {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    pub fn max_elapsed(mut self, max_elapsed: Duration) -> RetryPolicy {
+        self.max_elapsed = Some(max_elapsed);
+        self
+    }
+
+    /// Also retries auth and sign requests.
+    ///
+    /// An order request that timed out may still have started an order, retrying it then fails
+    /// with `alreadyInProgress` and cancels the first order.
+    pub fn retry_order_creation(mut self, retry: bool) -> RetryPolicy {
+        self.retry_order_creation = retry;
+        self
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    pub(crate) fn applies_to(&self, path: Option<ApiPath>) -> bool {
+        match path {
+            Some(ApiPath::Auth | ApiPath::Sign) => self.retry_order_creation,
+            Some(ApiPath::Collect | ApiPath::Cancel) => true,
+            None => false,
+        }
+    }
+
+    pub fn is_retryable(error: &Error) -> bool {
+        match error {
+            Error::ClientError {
+                error:
+                    ClientError {
+                        error_code: ErrorCode::InternalError | ErrorCode::Maintenance,
+                        ..
+                    },
+                ..
+            } => true,
+            Error::ReqwestError(err) => err.is_connect() || err.is_timeout(),
+            _ => false,
+        }
+    }
+
+    /// The delay before the next attempt, or `None` if `error` after `attempts` attempts and
+    /// `elapsed` time shouldn't be retried.
+    pub(crate) fn next_delay(
+        &self,
+        error: &Error,
+        attempts: u32,
+        elapsed: Duration,
+    ) -> Option<Duration> {
+        if attempts >= self.max_attempts || !Self::is_retryable(error) {
+            return None;
+        }
+
+        let ceiling = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempts - 1))
+            .min(self.max_backoff);
+        let delay = ceiling.mul_f64(jitter());
+
+        match self.max_elapsed {
+            Some(max_elapsed) if elapsed + delay > max_elapsed => None,
+            _ => Some(delay),
+        }
+    }
+}
+
+// A random factor in [0, 1), from the randomly seeded std hasher to avoid depending on rand.
+fn jitter() -> f64 {
+    let random = RandomState::new().build_hasher().finish();
+
+    (random >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::RetryPolicy;
+    use crate::response::{ClientError, ErrorCode};
+    use crate::{ApiPath, Error, HttpStatus};
+
+    fn client_error(error_code: ErrorCode) -> Error {
+        Error::ClientError {
+            status: HttpStatus::new(503),
+            error: ClientError {
+                error_code,
+                details: String::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_retry_policy() {
+        let policy = RetryPolicy::new(3).backoff(Duration::from_secs(1), Duration::from_secs(10));
+        let maintenance = client_error(ErrorCode::Maintenance);
+
+        for attempts in 1..3 {
+            let delay = policy
+                .next_delay(&maintenance, attempts, Duration::ZERO)
+                .expect("Expected a retry");
+            assert!(delay < Duration::from_secs(1 << (attempts - 1)));
+        }
+
+        assert_eq!(policy.next_delay(&maintenance, 3, Duration::ZERO), None);
+        assert_eq!(
+            policy.next_delay(
+                &client_error(ErrorCode::InvalidParameters),
+                1,
+                Duration::ZERO
+            ),
+            None
+        );
+
+        let policy = policy.max_elapsed(Duration::from_secs(5));
+        assert_eq!(
+            policy.next_delay(&maintenance, 1, Duration::from_secs(5)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_retry_order_creation() {
+        let policy = RetryPolicy::new(3);
+
+        assert!(policy.applies_to(Some(ApiPath::Collect)));
+        assert!(policy.applies_to(Some(ApiPath::Cancel)));
+        assert!(!policy.applies_to(Some(ApiPath::Auth)));
+        assert!(!policy.applies_to(Some(ApiPath::Sign)));
+
+        let policy = policy.retry_order_creation(true);
+        assert!(policy.applies_to(Some(ApiPath::Auth)));
+        assert!(policy.applies_to(Some(ApiPath::Sign)));
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn test_client_retries() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, ResponseTemplate};
+
+        use crate::golden::COLLECT_PENDING;
+        use crate::{ClientBuilder, Uuid};
+
+        let rp = crate::mock::MockRp::start().await;
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503).set_body_raw(
+                r#"{"errorCode":"maintenance","details":""}"#,
+                "application/json",
+            ))
+            .up_to_n_times(2)
+            .mount(rp.server())
+            .await;
+        rp.respond(ApiPath::Collect, COLLECT_PENDING).await;
+
+        let client = ClientBuilder::new(rp.endpoint())
+            .retry_policy(
+                RetryPolicy::new(3).backoff(Duration::from_millis(1), Duration::from_millis(10)),
+            )
+            .build();
+
+        client
+            .collect(Uuid::nil().into())
+            .await
+            .expect("Collect failed");
+
+        let requests = rp.server().received_requests().await.unwrap_or_default();
+        assert_eq!(requests.len(), 3);
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn test_order_creation_retries() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, ResponseTemplate};
+
+        use crate::golden::ORDER_RESPONSE;
+        use crate::mock::auth_request;
+        use crate::ClientBuilder;
+
+        for (retry_order_creation, expected) in [(false, 1), (true, 3)] {
+            let rp = crate::mock::MockRp::start().await;
+
+            Mock::given(method("POST"))
+                .respond_with(ResponseTemplate::new(503).set_body_raw(
+                    r#"{"errorCode":"maintenance","details":""}"#,
+                    "application/json",
+                ))
+                .up_to_n_times(2)
+                .mount(rp.server())
+                .await;
+            rp.respond(ApiPath::Auth, ORDER_RESPONSE).await;
+
+            let client = ClientBuilder::new(rp.endpoint())
+                .retry_policy(
+                    RetryPolicy::new(3)
+                        .backoff(Duration::from_millis(1), Duration::from_millis(10))
+                        .retry_order_creation(retry_order_creation),
+                )
+                .build();
+
+            let result = client.auth(auth_request(None)).await;
+            assert_eq!(result.is_ok(), retry_order_creation);
+
+            let requests = rp.server().received_requests().await.unwrap_or_default();
+            assert_eq!(requests.len(), expected);
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use std::collections::HashMap;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::oid_registry::OID_X509_SERIALNUMBER;
+use x509_parser::prelude::FromDer;
+
+use crate::request::{UserNonVisibleData, UserVisibleData};
+use crate::{Error, PersonalNumber};
+
+/// Details of the user certificate that made a signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertificateInfo {
+    pub issuer_cn: Option<String>,
+    pub subject_cn: Option<String>,
+    /// Serial number as lowercase hex.
+    pub serial_number: String,
+}
+
+/// What the user signed, from the `bankIdSignedData` element of a signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedData {
+    pub user_visible_data: Option<UserVisibleData>,
+    pub user_non_visible_data: Option<UserNonVisibleData>,
+    /// The serial number of the user certificate.
+    pub personal_number: Option<PersonalNumber>,
+    pub client_info: ClientInfo,
+}
+
+/// The BankID app that made a signature. Base64 encoded values are decoded.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientInfo {
+    /// `Identification` or `Signing`.
+    pub func_id: Option<String>,
+    /// e.g. "Personal=7.14.0.6&BankID_exe=7.14.0.6".
+    pub version: Option<String>,
+    /// The operating system, e.g. "IOS".
+    pub device_type: Option<String>,
+    /// The operating system version.
+    pub device_info: Option<String>,
+    pub uhi: Option<String>,
+    /// How the user authenticated, e.g. "pin".
+    pub user_authentication: Option<String>,
+}
+
+fn invalid(reason: impl std::fmt::Display) -> Error {
+    Error::InvalidSignature(reason.to_string())
+}
+
+/// Where in the signature XML something is, e.g. byte 120 in `/Signature/KeyInfo`.
+#[derive(Debug, Clone)]
+struct Location {
+    offset: u64,
+    path: String,
+}
+
+impl Location {
+    fn new(offset: u64, path: &[String]) -> Location {
+        Location {
+            offset,
+            path: format!("/{}", path.join("/")),
+        }
+    }
+
+    fn invalid(&self, reason: impl std::fmt::Display) -> Error {
+        invalid(format!(
+            "at byte {} in {}: {}",
+            self.offset, self.path, reason
+        ))
+    }
+}
+
+fn decode(value: &str) -> Result<Vec<u8>, base64::DecodeError> {
+    let value: String = value.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+
+    STANDARD.decode(value)
+}
+
+pub(crate) fn decode_base64(value: &str) -> Result<Vec<u8>, Error> {
+    decode(value).map_err(invalid)
+}
+
+// The DER of every certificate, with where it's found.
+fn certificates(xml: &str) -> Result<Vec<(Location, Vec<u8>)>, Error> {
+    let mut reader = Reader::from_str(xml);
+    let mut path = Vec::new();
+    let mut certificates = Vec::new();
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|err| Location::new(reader.error_position(), &path).invalid(err))?;
+
+        match event {
+            Event::Start(element) => {
+                path.push(String::from_utf8_lossy(element.local_name().as_ref()).into_owned());
+
+                if element.local_name().as_ref() == b"X509Certificate" {
+                    let location = Location::new(reader.buffer_position(), &path);
+                    let text = reader
+                        .read_text(element.name())
+                        .map_err(|err| location.invalid(err))?;
+                    let der = decode(&text).map_err(|err| location.invalid(err))?;
+
+                    certificates.push((location, der));
+                    path.pop();
+                }
+            }
+            Event::End(_) => {
+                path.pop();
+            }
+            Event::Eof => break,
+            _ => (),
+        }
+    }
+
+    Ok(certificates)
+}
+
+// The text of the elements in `bankIdSignedData`, by their path below it, e.g.
+// `clientInfo/funcId`.
+fn signed_data_elements(xml: &str) -> Result<HashMap<String, (Location, String)>, Error> {
+    let mut reader = Reader::from_str(xml);
+    let mut path = Vec::new();
+    let mut elements = HashMap::new();
+
+    loop {
+        let offset = reader.buffer_position();
+        let event = reader
+            .read_event()
+            .map_err(|err| Location::new(reader.error_position(), &path).invalid(err))?;
+
+        match event {
+            Event::Start(element) => {
+                path.push(String::from_utf8_lossy(element.local_name().as_ref()).into_owned());
+            }
+            Event::End(_) => {
+                path.pop();
+            }
+            Event::Text(text) => {
+                let location = Location::new(offset, &path);
+                let text = text.unescape().map_err(|err| location.invalid(err))?;
+
+                if let Some(start) = path.iter().position(|name| name == "bankIdSignedData") {
+                    if !text.trim().is_empty() {
+                        elements.insert(path[start + 1..].join("/"), (location, text.into_owned()));
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => (),
+        }
+    }
+
+    Ok(elements)
+}
+
+fn common_name<'a>(
+    mut names: impl Iterator<Item = &'a x509_parser::x509::AttributeTypeAndValue<'a>>,
+) -> Option<String> {
+    names
+        .next()
+        .and_then(|name| name.as_str().ok())
+        .map(str::to_owned)
+}
+
+fn decode_xml(signature: &str) -> Result<String, Error> {
+    String::from_utf8(decode_base64(signature)?).map_err(invalid)
+}
+
+// Parses every certificate in the signature and applies `f` to the user certificate.
+fn with_user_certificate<T>(xml: &str, f: impl Fn(&X509Certificate<'_>) -> T) -> Result<T, Error> {
+    let certificates = certificates(xml)?
+        .into_iter()
+        .map(|(location, der)| {
+            X509Certificate::from_der(&der)
+                .map(|(_, certificate)| (certificate.is_ca(), f(&certificate)))
+                .map_err(|err| location.invalid(err))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    // The user certificate is the only one in the chain that isn't a CA.
+    certificates
+        .into_iter()
+        .find(|(is_ca, _)| !is_ca)
+        .map(|(_, value)| value)
+        .ok_or_else(|| invalid("No user certificate in signature"))
+}
+
+/// Parses the end-user certificate out of the base64 encoded XML signature in
+/// `CompletionData::signature`.
+pub fn user_certificate(signature: &str) -> Result<CertificateInfo, Error> {
+    with_user_certificate(&decode_xml(signature)?, |certificate| CertificateInfo {
+        issuer_cn: common_name(certificate.issuer().iter_common_name()),
+        subject_cn: common_name(certificate.subject().iter_common_name()),
+        serial_number: certificate
+            .raw_serial()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect(),
+    })
+}
+
+/// Parses what the user signed out of the base64 encoded XML signature in
+/// `CompletionData::signature`.
+pub fn signed_data(signature: &str) -> Result<SignedData, Error> {
+    let xml = decode_xml(signature)?;
+    let mut elements = signed_data_elements(&xml)?;
+
+    let personal_number = with_user_certificate(&xml, |certificate| {
+        certificate
+            .subject()
+            .iter_by_oid(&OID_X509_SERIALNUMBER)
+            .next()
+            .and_then(|name| name.as_str().ok())
+            .map(PersonalNumber::parse)
+    })?
+    .transpose()
+    .map_err(|err| invalid(format!("user certificate serial number: {}", err)))?;
+
+    Ok(SignedData {
+        user_visible_data: take_element(&mut elements, "usrVisibleData", |text| {
+            UserVisibleData::from_base64(text)
+        })?,
+        user_non_visible_data: take_element(&mut elements, "usrNonVisibleData", |text| {
+            UserNonVisibleData::from_base64(text)
+        })?,
+        personal_number,
+        client_info: ClientInfo {
+            func_id: take_element(&mut elements, "clientInfo/funcId", plain)?,
+            version: take_element(&mut elements, "clientInfo/version", decode_text)?,
+            device_type: take_element(&mut elements, "clientInfo/env/ai/type", decode_text)?,
+            device_info: take_element(&mut elements, "clientInfo/env/ai/deviceInfo", decode_text)?,
+            uhi: take_element(&mut elements, "clientInfo/env/ai/uhi", plain)?,
+            user_authentication: take_element(&mut elements, "clientInfo/env/ai/uauth", plain)?,
+        },
+    })
+}
+
+fn take_element<T, E: std::fmt::Display>(
+    elements: &mut HashMap<String, (Location, String)>,
+    name: &str,
+    parse: impl FnOnce(&str) -> Result<T, E>,
+) -> Result<Option<T>, Error> {
+    elements
+        .remove(name)
+        .map(|(location, text)| parse(&text).map_err(|err| location.invalid(err)))
+        .transpose()
+}
+
+fn plain(text: &str) -> Result<String, std::convert::Infallible> {
+    Ok(text.to_owned())
+}
+
+fn decode_text(text: &str) -> Result<String, String> {
+    let bytes = decode(text).map_err(|err| err.to_string())?;
+
+    String::from_utf8(bytes).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+
+    use super::{signed_data, user_certificate};
+    use crate::PersonalNumber;
+
+    #[test]
+    fn test_user_certificate() {
+        let signature = STANDARD.encode(include_str!("./fixtures/signature.xml"));
+
+        let certificate = user_certificate(&signature).expect("Failed to parse certificate");
+
+        assert_eq!(
+            certificate.issuer_cn.as_deref(),
+            Some("Testbank A Customer CA1 v1 for BankID Test")
+        );
+        assert_eq!(certificate.subject_cn.as_deref(), Some("Tester Testsson"));
+        assert_eq!(certificate.serial_number, "1a2b3c4d5e6f");
+    }
+
+    #[test]
+    fn test_user_certificate_invalid() {
+        assert!(user_certificate("not base64").is_err());
+        assert!(user_certificate(&STANDARD.encode("<Signature>")).is_err());
+    }
+
+    #[test]
+    fn test_invalid_location() {
+        let error = |xml: &str| {
+            user_certificate(&STANDARD.encode(xml))
+                .expect_err("Expected an invalid signature")
+                .to_string()
+        };
+
+        assert_eq!(
+            error("<Signature><KeyInfo><X509Certificate>not base64!</X509Certificate>"),
+            "Invalid signature at byte 37 in /Signature/KeyInfo/X509Certificate: \
+             Invalid symbol 33, offset 9."
+        );
+        assert!(error("<Signature><KeyInfo></Signature>").contains("in /Signature/KeyInfo:"));
+    }
+
+    #[test]
+    fn test_signed_data() {
+        let signature = STANDARD.encode(include_str!("./fixtures/signature.xml"));
+
+        let data = signed_data(&signature).expect("Failed to parse signed data");
+
+        assert_eq!(
+            data.user_visible_data.as_ref().map(|data| data.text()),
+            Some("Transfer 100 SEK to Testbank")
+        );
+        assert_eq!(
+            data.user_non_visible_data
+                .as_ref()
+                .map(|data| data.as_bytes()),
+            Some(&b"order-4711"[..])
+        );
+        assert_eq!(
+            data.personal_number,
+            Some(PersonalNumber::parse("199001019802").expect("Parsing failed"))
+        );
+        assert_eq!(data.client_info.func_id.as_deref(), Some("Signing"));
+        assert_eq!(
+            data.client_info.version.as_deref(),
+            Some("Personal=7.14.0.6&BankID_exe=7.14.0.6")
+        );
+        assert_eq!(data.client_info.device_type.as_deref(), Some("IOS"));
+        assert_eq!(data.client_info.device_info.as_deref(), Some("17.1"));
+        assert_eq!(
+            data.client_info.uhi.as_deref(),
+            Some("OZvYM9VvyiAmG7NA5jU5zqGcVpo=")
+        );
+        assert_eq!(data.client_info.user_authentication.as_deref(), Some("pin"));
+
+        let xml = include_str!("./fixtures/signature.xml").replace(
+            "<usrNonVisibleData>b3JkZXItNDcxMQ==",
+            "<usrNonVisibleData>not base64!",
+        );
+        assert!(signed_data(&STANDARD.encode(xml))
+            .expect_err("Expected an invalid signature")
+            .to_string()
+            .contains("in /Signature/Object/bankIdSignedData/usrNonVisibleData:"));
+    }
+}
@@ -0,0 +1,244 @@
+//! Transport-free handling of collect responses, shared by the pollers in `flow`.
+
+use std::fmt::{self, Display};
+use std::time::Duration;
+
+use crate::flow::PollPolicy;
+use crate::response::{CollectHintCode, CollectResponse, CompletionData};
+use crate::OrderRef;
+
+/// A message from BankID's guidelines for relying parties that should be shown to the user.
+///
+/// Messages with A and B versions differ by device, choosing one is up to the frontend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UserMessage {
+    Rfa1,
+    Rfa3,
+    Rfa6,
+    Rfa8,
+    Rfa9,
+    Rfa13,
+    Rfa14,
+    Rfa16,
+    Rfa17,
+    Rfa21,
+    Rfa22,
+    Rfa23,
+}
+
+impl UserMessage {
+    /// The message for a pending or failed order with `hint_code`.
+    pub fn for_hint_code(hint_code: &CollectHintCode, pending: bool, autostarted: bool) -> Self {
+        match (pending, hint_code) {
+            (true, CollectHintCode::OutstandingTransaction) if autostarted => Self::Rfa13,
+            (true, CollectHintCode::OutstandingTransaction | CollectHintCode::NoClient) => {
+                Self::Rfa1
+            }
+            (true, CollectHintCode::Started) => Self::Rfa14,
+            (true, CollectHintCode::UserSign) => Self::Rfa9,
+            (true, CollectHintCode::UserMrtd) => Self::Rfa23,
+            (true, _) => Self::Rfa21,
+            (false, CollectHintCode::ExpiredTransaction) => Self::Rfa8,
+            (false, CollectHintCode::CertificateErr) => Self::Rfa16,
+            (false, CollectHintCode::UserCancel) => Self::Rfa6,
+            (false, CollectHintCode::Canceled) => Self::Rfa3,
+            (false, CollectHintCode::StartFailed) => Self::Rfa17,
+            (false, _) => Self::Rfa22,
+        }
+    }
+
+    /// The number used in the guidelines, e.g. "RFA9".
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Rfa1 => "RFA1",
+            Self::Rfa3 => "RFA3",
+            Self::Rfa6 => "RFA6",
+            Self::Rfa8 => "RFA8",
+            Self::Rfa9 => "RFA9",
+            Self::Rfa13 => "RFA13",
+            Self::Rfa14 => "RFA14",
+            Self::Rfa16 => "RFA16",
+            Self::Rfa17 => "RFA17",
+            Self::Rfa21 => "RFA21",
+            Self::Rfa22 => "RFA22",
+            Self::Rfa23 => "RFA23",
+        }
+    }
+}
+
+impl Display for UserMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+/// What a collect response means for the order.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transition {
+    /// Collect again after `next_collect`. `changed` is false if the hint code is the same as in
+    /// the previous response.
+    Pending {
+        hint_code: CollectHintCode,
+        message: UserMessage,
+        changed: bool,
+        next_collect: Duration,
+    },
+    Failed {
+        hint_code: CollectHintCode,
+        message: UserMessage,
+    },
+    Complete {
+        completion_data: CompletionData,
+    },
+}
+
+/// Tracks an order through its collect responses, without doing any I/O or timing.
+///
+/// The deadline of the `PollPolicy` is left to the caller.
+#[derive(Debug, Clone)]
+pub struct CollectStateMachine {
+    order_ref: OrderRef,
+    policy: PollPolicy,
+    autostarted: bool,
+    interval: Option<Duration>,
+    last_hint_code: Option<CollectHintCode>,
+    finished: bool,
+}
+
+impl CollectStateMachine {
+    pub fn new(order_ref: OrderRef, policy: PollPolicy) -> CollectStateMachine {
+        CollectStateMachine {
+            order_ref,
+            policy,
+            autostarted: false,
+            interval: None,
+            last_hint_code: None,
+            finished: false,
+        }
+    }
+
+    /// Whether the app was launched with the auto start token, which changes the messages.
+    pub fn autostarted(mut self, autostarted: bool) -> CollectStateMachine {
+        self.autostarted = autostarted;
+        self
+    }
+
+    pub fn order_ref(&self) -> OrderRef {
+        self.order_ref
+    }
+
+    /// Whether the order has completed or failed.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn apply(&mut self, response: CollectResponse) -> Transition {
+        match response {
+            CollectResponse::Pending { hint_code, .. } => {
+                let previous = self.interval.unwrap_or_else(|| self.policy.interval());
+                let next_collect = self.policy.next_interval(previous, &hint_code);
+                let changed = self.last_hint_code.as_ref() != Some(&hint_code);
+
+                self.interval = Some(next_collect);
+                self.last_hint_code = Some(hint_code.to_owned());
+
+                Transition::Pending {
+                    message: UserMessage::for_hint_code(&hint_code, true, self.autostarted),
+                    hint_code,
+                    changed,
+                    next_collect,
+                }
+            }
+            CollectResponse::Failed { hint_code, .. } => {
+                self.finished = true;
+
+                Transition::Failed {
+                    message: UserMessage::for_hint_code(&hint_code, false, self.autostarted),
+                    hint_code,
+                }
+            }
+            CollectResponse::Complete {
+                completion_data, ..
+            } => {
+                self.finished = true;
+
+                Transition::Complete { completion_data }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{CollectStateMachine, Transition, UserMessage};
+    use crate::flow::PollPolicy;
+    use crate::golden::COLLECT_COMPLETE;
+    use crate::response::{CollectHintCode, CollectResponse};
+    use crate::{OrderRef, Uuid};
+
+    #[test]
+    fn test_collect_state_machine() {
+        let order_ref = OrderRef::from(Uuid::nil());
+        let policy = PollPolicy::new(Duration::from_secs(2))
+            .outstanding_transaction_backoff(2.0, Duration::from_secs(5));
+        let mut machine = CollectStateMachine::new(order_ref, policy).autostarted(true);
+        let pending = |hint_code| CollectResponse::Pending {
+            hint_code,
+            order_ref,
+        };
+
+        assert_eq!(
+            machine.apply(pending(CollectHintCode::OutstandingTransaction)),
+            Transition::Pending {
+                hint_code: CollectHintCode::OutstandingTransaction,
+                message: UserMessage::Rfa13,
+                changed: true,
+                next_collect: Duration::from_secs(4),
+            }
+        );
+        assert!(matches!(
+            machine.apply(pending(CollectHintCode::OutstandingTransaction)),
+            Transition::Pending { changed: false, .. }
+        ));
+        assert!(matches!(
+            machine.apply(pending(CollectHintCode::UserSign)),
+            Transition::Pending {
+                message: UserMessage::Rfa9,
+                changed: true,
+                ..
+            }
+        ));
+        assert!(!machine.is_finished());
+
+        assert!(matches!(
+            machine.apply(
+                serde_json::from_str(COLLECT_COMPLETE).expect("Failed to deserialize fixture")
+            ),
+            Transition::Complete { .. }
+        ));
+        assert!(machine.is_finished());
+    }
+
+    #[test]
+    fn test_failed_messages() {
+        for (hint_code, message) in [
+            (CollectHintCode::ExpiredTransaction, UserMessage::Rfa8),
+            (CollectHintCode::CertificateErr, UserMessage::Rfa16),
+            (CollectHintCode::UserCancel, UserMessage::Rfa6),
+            (CollectHintCode::Canceled, UserMessage::Rfa3),
+            (CollectHintCode::StartFailed, UserMessage::Rfa17),
+            (
+                CollectHintCode::Unknown("new".to_owned()),
+                UserMessage::Rfa22,
+            ),
+        ] {
+            assert_eq!(
+                UserMessage::for_hint_code(&hint_code, false, false),
+                message
+            );
+        }
+    }
+}
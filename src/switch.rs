@@ -0,0 +1,188 @@
+//! Replacing the client of a running service, e.g. to promote it from test to production.
+//!
+//! This isn't a swap inside a live `Client`: the endpoint and identity of a `Client` are fixed
+//! when it's built. Only code that gets its client from `ClientSwitch::current` for every order
+//! sees the new one, code holding on to a `Client` or an earlier `current` keeps using the old.
+
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use tokio::time::Instant;
+
+use crate::{logging, Client, Environment};
+
+// How often `switch` checks whether the previous client is still in use.
+const DRAIN_INTERVAL: Duration = Duration::from_millis(50);
+
+/// What `ClientSwitch::switch` did, for the audit log.
+#[must_use = "a client switch should be kept in the audit log"]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwitchRecord {
+    pub from: Environment,
+    pub to: Environment,
+    pub reason: String,
+    pub switched_at: SystemTime,
+    /// How long it took until the previous client was no longer in use, `None` if it still was
+    /// when the drain timeout passed.
+    pub drained_in: Option<Duration>,
+}
+
+/// The client of a service, which can be replaced while the service is running.
+///
+/// An order ref is only valid in the environment it was created in, so an order should keep the
+/// client from `current` from start to finish.
+#[derive(Debug)]
+pub struct ClientSwitch {
+    current: RwLock<Arc<Client>>,
+}
+
+impl ClientSwitch {
+    pub fn new(client: Client) -> ClientSwitch {
+        ClientSwitch {
+            current: RwLock::new(Arc::new(client)),
+        }
+    }
+
+    /// The client to use for a new order.
+    pub fn current(&self) -> Arc<Client> {
+        // The lock only guards swapping an `Arc`, so it's still usable if a holder panicked.
+        Arc::clone(
+            &self
+                .current
+                .read()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        )
+    }
+
+    /// Makes `client` the one used for new orders, then waits until the previous client is no
+    /// longer held by any order, for at most `drain_timeout`.
+    ///
+    /// The new client is in place as soon as the returned future is first polled, dropping the
+    /// future only stops the waiting. The switch, and a drain timeout, are logged as warnings to
+    /// the `logging::TARGET` target.
+    pub async fn switch(
+        &self,
+        client: Client,
+        reason: &str,
+        drain_timeout: Duration,
+    ) -> SwitchRecord {
+        let to = client.environment();
+        let previous = std::mem::replace(
+            &mut *self
+                .current
+                .write()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+            Arc::new(client),
+        );
+        let from = previous.environment();
+        let switched_at = SystemTime::now();
+        let started = Instant::now();
+
+        log::warn!(
+            target: logging::TARGET,
+            "Switched BankID client from {:?} to {:?}: {}",
+            from,
+            to,
+            reason
+        );
+
+        let mut drained_in = None;
+
+        loop {
+            if Arc::strong_count(&previous) == 1 {
+                drained_in = Some(started.elapsed());
+                break;
+            }
+
+            if started.elapsed() >= drain_timeout {
+                log::warn!(
+                    target: logging::TARGET,
+                    "Previous BankID client ({:?}) still in use {:?} after the switch",
+                    from,
+                    drain_timeout
+                );
+                break;
+            }
+
+            tokio::time::sleep(DRAIN_INTERVAL.min(drain_timeout.saturating_sub(started.elapsed())))
+                .await;
+        }
+
+        SwitchRecord {
+            from,
+            to,
+            reason: reason.to_owned(),
+            switched_at,
+            drained_in,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::ClientSwitch;
+    use crate::golden::COLLECT_PENDING;
+    use crate::mock::MockRp;
+    use crate::{ApiPath, Client, Uuid};
+
+    #[tokio::test]
+    async fn test_switch_drains() {
+        let rp_a = MockRp::start().await;
+        let rp_b = MockRp::start().await;
+        rp_b.respond(ApiPath::Collect, COLLECT_PENDING).await;
+
+        let switch = Arc::new(ClientSwitch::new(Client::new(rp_a.endpoint())));
+        let in_flight = switch.current();
+
+        let switching = tokio::spawn({
+            let switch = Arc::clone(&switch);
+            let client = Client::new(rp_b.endpoint());
+            async move {
+                switch
+                    .switch(client, "promote", Duration::from_secs(10))
+                    .await
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        switch
+            .current()
+            .collect(Uuid::nil().into())
+            .await
+            .expect("Collect failed");
+        assert!(!switching.is_finished());
+
+        drop(in_flight);
+        let record = switching.await.expect("Switch failed");
+        assert_eq!(record.reason, "promote");
+        assert!(record
+            .drained_in
+            .is_some_and(|drained_in| drained_in < Duration::from_secs(10)));
+        assert_eq!(
+            rp_a.server().received_requests().await.map(|r| r.len()),
+            Some(0)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_switch_drain_timeout() {
+        let rp = MockRp::start().await;
+
+        let switch = ClientSwitch::new(Client::new(rp.endpoint()));
+        let leaked = switch.current();
+
+        let record = switch
+            .switch(
+                Client::new(rp.endpoint()),
+                "promote",
+                Duration::from_secs(5),
+            )
+            .await;
+
+        assert_eq!(record.drained_in, None);
+        assert!(!Arc::ptr_eq(&leaked, &switch.current()));
+    }
+}
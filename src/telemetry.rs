@@ -0,0 +1,227 @@
+//! Hooks for observing the requests a client makes.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::flow::CancelReason;
+use crate::response::{Envelope, ErrorCode};
+use crate::{ApiPath, ClientBuilder, Error, HttpStatus, Metadata};
+
+/// One request to the RP API, successful or not. Retries are separate requests.
+#[derive(Debug, Clone)]
+pub struct RequestEvent {
+    pub path: ApiPath,
+    /// `None` if no response was received.
+    pub status: Option<HttpStatus>,
+    pub error_code: Option<ErrorCode>,
+    pub duration: Duration,
+    /// Why the relying party cancelled the order, for cancel requests.
+    pub cancel_reason: Option<CancelReason>,
+    /// The metadata of the order, for requests made through an `OrderHandle` that has some.
+    pub metadata: Option<Metadata>,
+}
+
+/// What a request is made for, passed on to its `RequestEvent`.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct RequestContext<'a> {
+    pub(crate) cancel_reason: Option<&'a CancelReason>,
+    pub(crate) metadata: Option<&'a Metadata>,
+}
+
+/// A step of an order driven by the polling helpers in `flow`, e.g. `Client::authenticate`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Phase {
+    /// From calling the helper until the order was created, including retries.
+    CreateOrder,
+    /// From the hint code `started` until `userSign`, i.e. how long the user took to get from
+    /// opening the app to being asked for their security code.
+    StartedToUserSign,
+    /// From the order being created until it completed.
+    Complete,
+}
+
+impl Phase {
+    /// A short name for metrics, e.g. "createOrder".
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::CreateOrder => "createOrder",
+            Self::StartedToUserSign => "startedToUserSign",
+            Self::Complete => "complete",
+        }
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// How long one phase of an order took.
+#[derive(Debug, Clone)]
+pub struct PhaseEvent {
+    pub phase: Phase,
+    pub duration: Duration,
+    /// The metadata of the order, for orders waited on through an `OrderHandle` that has some.
+    pub metadata: Option<Metadata>,
+}
+
+impl RequestEvent {
+    pub(crate) fn new<T>(
+        path: ApiPath,
+        result: &Result<Envelope<T>, Error>,
+        duration: Duration,
+    ) -> RequestEvent {
+        let (status, error_code) = match result {
+            Ok(envelope) => (Some(envelope.status), None),
+            Err(Error::ClientError { status, error }) => {
+                (Some(*status), Some(error.error_code.to_owned()))
+            }
+            Err(Error::UnexpectedResponse { status, .. }) => (Some(*status), None),
+            Err(_) => (None, None),
+        };
+
+        RequestEvent {
+            path,
+            status,
+            error_code,
+            duration,
+            cancel_reason: None,
+            metadata: None,
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.status.is_some_and(|status| status.is_success())
+    }
+}
+
+pub trait MetricsHook: Send + Sync {
+    fn record(&self, event: &RequestEvent);
+
+    /// Called when a polling helper in `flow` finishes a phase of an order. Does nothing by
+    /// default.
+    fn record_phase(&self, _event: &PhaseEvent) {}
+}
+
+#[derive(Clone)]
+pub(crate) struct SharedHook(Arc<dyn MetricsHook>);
+
+impl SharedHook {
+    pub(crate) fn record(&self, event: &RequestEvent) {
+        self.0.record(event)
+    }
+
+    pub(crate) fn record_phase(&self, event: &PhaseEvent) {
+        self.0.record_phase(event)
+    }
+}
+
+impl fmt::Debug for SharedHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "MetricsHook")
+    }
+}
+
+impl ClientBuilder {
+    /// Reports every request the client makes to `hook`.
+    pub fn metrics_hook<H>(mut self, hook: H) -> ClientBuilder
+    where
+        H: MetricsHook + 'static,
+    {
+        self.metrics_hook = Some(SharedHook(Arc::new(hook)));
+        self
+    }
+}
+
+/// Records requests with the `metrics` facade, as the `bankid_requests_total` counter and the
+/// `bankid_request_duration_seconds` histogram, labeled with the path and status.
+///
+/// Cancellations with a reason are also counted by `bankid_cancellations_total`, labeled with the
+/// reason. Free text reasons are all labeled `other`, to keep the number of series bounded.
+///
+/// Phases go to the `bankid_phase_duration_seconds` histogram, labeled with the phase.
+#[cfg(feature = "metrics")]
+#[cfg_attr(docsrs, doc(cfg(feature = "metrics")))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetricsFacade;
+
+#[cfg(feature = "metrics")]
+impl MetricsHook for MetricsFacade {
+    fn record(&self, event: &RequestEvent) {
+        let labels = [
+            ("path", event.path.as_str().to_owned()),
+            (
+                "status",
+                event
+                    .status
+                    .map_or_else(|| "error".to_owned(), |status| status.to_string()),
+            ),
+        ];
+
+        metrics::counter!("bankid_requests_total", &labels).increment(1);
+        metrics::histogram!("bankid_request_duration_seconds", &labels)
+            .record(event.duration.as_secs_f64());
+
+        if let Some(reason) = &event.cancel_reason {
+            metrics::counter!("bankid_cancellations_total", "reason" => reason_label(reason))
+                .increment(1);
+        }
+    }
+
+    fn record_phase(&self, event: &PhaseEvent) {
+        metrics::histogram!("bankid_phase_duration_seconds", "phase" => event.phase.as_str())
+            .record(event.duration.as_secs_f64());
+    }
+}
+
+// A label value for `reason`, from a fixed set since `Other` holds free text.
+#[cfg(feature = "metrics")]
+fn reason_label(reason: &CancelReason) -> &'static str {
+    match reason {
+        CancelReason::Timeout => "timeout",
+        CancelReason::NavigatedAway => "navigatedAway",
+        CancelReason::Duplicate => "duplicate",
+        CancelReason::Other(_) => "other",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::RequestEvent;
+    use crate::response::{ClientError, Envelope, ErrorCode};
+    use crate::{ApiPath, Error, HttpStatus};
+
+    #[test]
+    fn test_request_event() {
+        let result: Result<Envelope<()>, Error> = Err(Error::ClientError {
+            status: HttpStatus::new(503),
+            error: ClientError {
+                error_code: ErrorCode::Maintenance,
+                details: String::new(),
+            },
+        });
+
+        let event = RequestEvent::new(ApiPath::Collect, &result, Duration::from_millis(5));
+
+        assert_eq!(event.status, Some(HttpStatus::new(503)));
+        assert_eq!(event.error_code, Some(ErrorCode::Maintenance));
+        assert!(!event.is_success());
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_reason_label() {
+        use super::reason_label;
+        use crate::flow::CancelReason;
+
+        assert_eq!(reason_label(&CancelReason::Timeout), "timeout");
+        assert_eq!(
+            reason_label(&CancelReason::Other("user 199001019802 left".to_owned())),
+            "other"
+        );
+    }
+}
@@ -0,0 +1,57 @@
+//! Helpers for working against the BankID test environment.
+
+use crate::PersonalNumber;
+
+/// Certificate policy OIDs used for test BankIDs, as documented in the relying party guidelines.
+pub mod certificate_policies {
+    pub const BANKID_ON_FILE: &str = "1.2.3.4.5";
+    pub const BANKID_ON_SMART_CARD: &str = "1.2.3.4.10";
+    pub const MOBILE_BANKID: &str = "1.2.3.4.25";
+    pub const NORDEA_EID_ON_FILE_AND_SMART_CARD: &str = "1.2.752.71.1.3";
+    pub const TEST_BANKID_FOR_SOME_BANKS: &str = "1.2.752.60.1.6";
+}
+
+/// Personal numbers from Skatteverket's series of test numbers, which are never assigned to real
+/// persons and can be used when creating test BankIDs.
+pub const DEMO_PERSONAL_NUMBERS: [PersonalNumber; 3] = [
+    PersonalNumber {
+        year: 1990,
+        month: 1,
+        day: 1,
+        last_four_digits: 9802,
+    },
+    PersonalNumber {
+        year: 1985,
+        month: 6,
+        day: 15,
+        last_four_digits: 9816,
+    },
+    PersonalNumber {
+        year: 1970,
+        month: 3,
+        day: 12,
+        last_four_digits: 9827,
+    },
+];
+
+/// Returns `true` if the personal number belongs to Skatteverket's test series, which uses birth
+/// numbers 980-999.
+pub fn is_test_personal_number(personal_number: &PersonalNumber) -> bool {
+    personal_number.last_four_digits / 10 >= 980
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_test_personal_number, DEMO_PERSONAL_NUMBERS};
+    use crate::PersonalNumber;
+
+    #[test]
+    fn test_is_test_personal_number() {
+        for personal_number in DEMO_PERSONAL_NUMBERS {
+            assert!(is_test_personal_number(&personal_number));
+        }
+
+        let personal_number = PersonalNumber::parse("198710101232").expect("Parsing failed");
+        assert!(!is_test_personal_number(&personal_number));
+    }
+}
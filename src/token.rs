@@ -0,0 +1,168 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Uuid};
+
+// A UUID issued by BankID, as its own type so that tokens can't be mixed up.
+macro_rules! token {
+    ($(#[$attr:meta])* $name:ident) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        #[serde(transparent)]
+        pub struct $name(Uuid);
+
+        impl $name {
+            pub fn as_uuid(&self) -> &Uuid {
+                &self.0
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.0.to_hyphenated())
+            }
+        }
+
+        impl From<Uuid> for $name {
+            fn from(uuid: Uuid) -> Self {
+                $name(uuid)
+            }
+        }
+
+        impl From<$name> for Uuid {
+            fn from(token: $name) -> Self {
+                token.0
+            }
+        }
+    };
+}
+
+token!(
+    /// Reference to an auth or sign order.
+    OrderRef
+);
+
+token!(
+    /// Token for launching the BankID app on the same device, see `autostart`.
+    AutoStartToken
+);
+
+token!(
+    /// The public part of the animated QR code, see `qr`.
+    QrStartToken
+);
+
+/// The secret the animated QR code is derived from, never show it to the user.
+///
+/// Redacted from `Debug`. With the `zeroize` feature it's zeroed on drop, and isn't `Copy`.
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(not(feature = "zeroize"), derive(Copy))]
+#[serde(transparent)]
+pub struct QrStartSecret(SecretUuid);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+struct SecretUuid(Uuid);
+
+#[cfg(feature = "zeroize")]
+impl zeroize::DefaultIsZeroes for SecretUuid {}
+
+impl QrStartSecret {
+    pub fn as_uuid(&self) -> &Uuid {
+        &self.0 .0
+    }
+}
+
+impl fmt::Debug for QrStartSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "QrStartSecret(<redacted>)")
+    }
+}
+
+impl Display for QrStartSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0 .0.to_hyphenated())
+    }
+}
+
+impl From<Uuid> for QrStartSecret {
+    fn from(uuid: Uuid) -> Self {
+        QrStartSecret(SecretUuid(uuid))
+    }
+}
+
+impl From<QrStartSecret> for Uuid {
+    fn from(secret: QrStartSecret) -> Self {
+        secret.0 .0
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for QrStartSecret {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(&mut self.0);
+    }
+}
+
+impl OrderRef {
+    /// Parses an order ref in any of the common UUID notations.
+    pub fn parse(s: &str) -> Result<Self, Error> {
+        Uuid::parse_str(s.trim())
+            .map(OrderRef)
+            .map_err(|_| Error::InvalidOrderRef("Order ref is not a valid UUID"))
+    }
+
+    /// The first eight characters, for logs.
+    pub fn short(&self) -> String {
+        self.to_string()[..8].to_owned()
+    }
+}
+
+impl FromStr for OrderRef {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AutoStartToken, OrderRef};
+    use crate::response::OrderResponse;
+    use crate::Uuid;
+
+    #[test]
+    fn test_order_ref_parse() {
+        let expected = "131daac9-16c6-4618-beb0-365768f37288";
+
+        for input in [
+            expected,
+            "131DAAC9-16C6-4618-BEB0-365768F37288",
+            "131daac916c64618beb0365768f37288",
+            " 131daac9-16c6-4618-beb0-365768f37288\n",
+        ] {
+            let order_ref = OrderRef::parse(input).expect("Parsing failed");
+            assert_eq!(order_ref.to_string(), expected);
+            assert_eq!(order_ref.short(), "131daac9");
+        }
+
+        assert!(OrderRef::parse("131daac9").is_err());
+    }
+
+    #[test]
+    fn test_tokens() {
+        let order: OrderResponse =
+            serde_json::from_str(crate::golden::ORDER_RESPONSE).expect("Failed to deserialize");
+        let uuid = Uuid::from(order.auto_start_token);
+
+        assert_eq!(AutoStartToken::from(uuid), order.auto_start_token);
+        assert_eq!(order.auto_start_token.to_string(), uuid.to_string());
+        assert_eq!(
+            serde_json::to_value(order.order_ref).expect("Failed to serialize"),
+            serde_json::Value::String(order.order_ref.to_string())
+        );
+    }
+}